@@ -0,0 +1,234 @@
+use std::{env, path::{Path, PathBuf}, collections::BTreeMap, process};
+use kasino::blackjack::engine::{Round, SeatBet, Shoe, ShoeRng, Phase, Event, Outcome, PlayingCard, init_shoe, seeded_rng, random_seed};
+use kasino::blackjack::money::Money;
+use kasino::blackjack::rules::{TableRules, RULES_FILE, load_table_rules};
+use kasino::blackjack::strategy::{Strategy, PlayerView, BankrollView, STRATEGY_NAMES, by_name};
+
+// Plays blackjack rounds without a window and reports how the chosen strategy does against the table rules
+//
+// cargo run --release --bin kasino-sim -- --rounds 1000000 --strategy basic --rules assets/table_rules.ron --seed 1234
+
+const DEFAULT_ROUNDS: u64 = 1_000_000;
+// Every round starts with this much money so the strategy is never held back by its bankroll
+const BANKROLL: Money = Money::from_units(1000);
+
+struct Options {
+    rounds: u64,
+    strategy: String,
+    rules: Option<String>,
+    seed: Option<u64>,
+}
+
+#[derive(Default)]
+struct Stats {
+    rounds: u64,
+    hands: u64,
+    bets: f64, // sum of the initial bets, in table minimums
+    wagered: f64,
+    net: f64,
+    net_squared: f64, // sum of the squared round results, in table minimums
+    player_busts: u64,
+    dealer_busts: u64,
+    dealer_played: u64,
+    outcomes: BTreeMap<String, u64>,
+    results: BTreeMap<i32, u64>, // round result in half table minimums
+    shoes: u64,
+}
+
+// The game's own rules file: in the project directory under cargo run, next to the executable otherwise
+fn default_rules_path() -> PathBuf {
+    let base = match env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)).unwrap_or_default(),
+    };
+    base.join("assets").join(RULES_FILE)
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            eprintln!("Usage: kasino-sim [--rounds N] [--strategy {}] [--rules FILE] [--seed N]", STRATEGY_NAMES.join("|"));
+            process::exit(2);
+        }
+    };
+
+    let rules = match &options.rules {
+        Some(path) => match TableRules::load(Path::new(path)) {
+            Ok(rules) => rules,
+            Err(error) => {
+                eprintln!("Invalid table rules in {}: {}", path, error);
+                process::exit(1);
+            }
+        },
+        None => load_table_rules(&default_rules_path()),
+    };
+
+    let strategy = match by_name(&options.strategy, &rules) {
+        Some(strategy) => strategy,
+        None => {
+            eprintln!("Unknown strategy {}, pick one of {}", options.strategy, STRATEGY_NAMES.join(", "));
+            process::exit(2);
+        }
+    };
+
+    let seed = options.seed.or(rules.seed).unwrap_or_else(random_seed);
+    println!("Simulating {} rounds with the {} strategy, seed {}", options.rounds, options.strategy, seed);
+    let stats = simulate(options.rounds, strategy.as_ref(), &rules, &mut seeded_rng(seed));
+    report(&stats);
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options { rounds: DEFAULT_ROUNDS, strategy: "basic".to_string(), rules: None, seed: None };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--rounds" => {
+                let rounds = value()?;
+                options.rounds = rounds.replace('_', "").parse().map_err(|_| format!("--rounds must be a whole number, got {}", rounds))?;
+            },
+            "--strategy" => options.strategy = value()?,
+            "--rules" => options.rules = Some(value()?),
+            "--seed" => {
+                let seed = value()?;
+                options.seed = Some(seed.parse().map_err(|_| format!("--seed must be a whole number, got {}", seed))?);
+            },
+            _ => return Err(format!("Unknown argument {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+// Bets what the strategy asks for and leaves the side bets alone
+fn simulate(rounds: u64, strategy: &dyn Strategy, rules: &TableRules, rng: &mut ShoeRng) -> Stats {
+    let mut stats = Stats::default();
+    let mut shoe: Shoe = init_shoe(rules.decks, rules.penetration, rng);
+    stats.shoes = 1;
+    let mut seen: Vec<PlayingCard> = Vec::new(); // the face up cards of the current shoe
+
+    for _ in 0..rounds {
+        // The simulated player keeps no history, every round starts from the same bankroll
+        let bet = strategy.bet(&BankrollView { money: BANKROLL, history: &[], seen: &seen, rules });
+        let seat = SeatBet { bet, side_bets: vec![Money::ZERO; rules.side_bets.len()] };
+        // A single seat plays against the dealer
+        let mut bankrolls = [BANKROLL];
+        let (mut round, mut events) = match Round::start(vec![seat], &mut bankrolls, &mut shoe, rng, rules) {
+            Ok(started) => started,
+            Err(error) => {
+                eprintln!("Could not start a round: {}", error);
+                process::exit(1);
+            }
+        };
+        see(&events, &mut seen);
+        while round.phase != Phase::Finished {
+            let action = strategy.decide(&PlayerView::new(&round, bankrolls[0], &seen, rules));
+            match round.act(action, &mut bankrolls, &mut shoe, rng, rules) {
+                Ok(more) => {
+                    see(&more, &mut seen);
+                    events.extend(more);
+                },
+                Err(error) => {
+                    eprintln!("The strategy asked for {:?}: {}", action, error);
+                    process::exit(1);
+                }
+            }
+        }
+        record(&mut stats, &events, bet, rules.min_bet);
+        if round.clear(&mut shoe, rng, rules) {
+            stats.shoes += 1;
+            seen.clear();
+        }
+    }
+    stats
+}
+
+// Keeps track of the cards a player at the table would have seen since the shuffle
+fn see(events: &[Event], seen: &mut Vec<PlayingCard>) {
+    for event in events.iter() {
+        match event {
+            Event::PlayerCard { card, .. } | Event::DealerCard { card, face_down: false } | Event::HoleCardRevealed(card) => seen.push(*card),
+            Event::DiscardsReshuffled => seen.clear(),
+            _ => {},
+        }
+    }
+}
+
+// Results are counted in table minimums, so that bets spread by a strategy add up
+fn record(stats: &mut Stats, events: &[Event], bet: Money, unit: Money) {
+    let units = |amount: Money| amount.as_f64() / unit.as_f64();
+    for event in events.iter() {
+        match event {
+            Event::PlayerBust { .. } => stats.player_busts += 1,
+            Event::DealerBust => stats.dealer_busts += 1,
+            Event::DealerHits | Event::DealerStands => {},
+            Event::RoundOver(settlements) => {
+                let settlement = &settlements[0];
+                let result = units(settlement.net());
+                stats.rounds += 1;
+                stats.hands += settlement.outcomes.len() as u64;
+                stats.bets += units(bet);
+                stats.wagered += units(settlement.wagered);
+                stats.net += result;
+                stats.net_squared += result * result;
+                for outcome in settlement.outcomes.iter() {
+                    *stats.outcomes.entry(outcome_name(outcome)).or_insert(0) += 1;
+                }
+                *stats.results.entry((result * 2.0).round() as i32).or_insert(0) += 1;
+            },
+            _ => {},
+        }
+    }
+    // The dealer only draws to a hand when there's a live player hand left to beat
+    if events.iter().any(|event| matches!(event, Event::DealerHits | Event::DealerStands | Event::DealerBust)) {
+        stats.dealer_played += 1;
+    }
+}
+
+fn outcome_name(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Win => "Win".to_string(),
+        Outcome::Blackjack => "Blackjack".to_string(),
+        Outcome::Push => "Push".to_string(),
+        Outcome::Lose => "Lose".to_string(),
+        Outcome::Surrendered => "Surrendered".to_string(),
+        Outcome::EvenMoney => "Even money".to_string(),
+    }
+}
+
+fn report(stats: &Stats) {
+    if stats.rounds == 0 {
+        println!("No rounds played");
+        return;
+    }
+    let rounds = stats.rounds as f64;
+    let mean = stats.net / rounds; // in table minimums per round
+    let variance = stats.net_squared / rounds - mean * mean;
+
+    println!();
+    println!("Rounds played      {}", stats.rounds);
+    println!("Hands played       {}", stats.hands);
+    println!("Shoes used         {}", stats.shoes);
+    println!("Total wagered      {:.1} table minimums", stats.wagered);
+    println!("Net result         {:+.1} table minimums", stats.net);
+    println!("House edge         {:.3}% of the initial bet", -stats.net / stats.bets * 100.0);
+    println!("                   {:.3}% of the total wagered", -stats.net / stats.wagered * 100.0);
+    println!("Variance           {:.4} per round", variance);
+    println!("Standard deviation {:.4} per round", variance.sqrt());
+    println!("Standard error     {:.3}% on the house edge", (variance / rounds).sqrt() / (stats.bets / rounds) * 100.0);
+    println!("Player bust rate   {:.2}% of hands", stats.player_busts as f64 / stats.hands as f64 * 100.0);
+    println!("Dealer bust rate   {:.2}% of the hands the dealer played out", stats.dealer_busts as f64 / stats.dealer_played.max(1) as f64 * 100.0);
+
+    println!();
+    println!("Hand outcomes");
+    for (outcome, count) in stats.outcomes.iter() {
+        println!("  {:<12} {:>10} {:>7.2}%", outcome, count, *count as f64 / stats.hands as f64 * 100.0);
+    }
+
+    println!();
+    println!("Round results, in table minimums");
+    for (result, count) in stats.results.iter() {
+        println!("  {:>+5.1} {:>10} {:>7.2}%", *result as f64 / 2.0, count, *count as f64 / rounds * 100.0);
+    }
+}
@@ -1,41 +1,70 @@
-use std::{fmt, cmp};
-use bevy::prelude::*;
-use rand::prelude::SliceRandom;
-use crate::{GameTextures, SFXPlayCard, Player, SPRITE_SCALE};
+use std::{collections::VecDeque, marker::PhantomData, ops::Deref};
+use bevy::{prelude::*, ecs::system::SystemParam, window::WindowCloseRequested};
+use crate::{GameTextures, SFXPlayCard, Players, Player, GameRng, SPRITE_SCALE};
+
+use kasino::blackjack::{engine, money, rules, basic_strategy, trainer, counting, strategy};
+pub use rules::TableRules;
+use money::Money;
+use basic_strategy::BasicStrategy;
+use trainer::{Trainer, Decision};
+use counting::{CardCounter, CountingSystem};
+use strategy::{Strategy, PlayerView, BankrollView};
+use engine::{Round, SeatBet, Shoe, ShoeRng, PlayingCard, CardSuit, Phase, Action, ActionError, Event, Outcome, init_shoe, HandEval};
 
 impl Plugin for BlackjackPlugin {
     fn build(&self, app: &mut App) {
         app
+        .insert_resource(load_rules())
         .add_startup_system(setup_system)
-        .add_state(BlackjackState::PreGame)
+        .add_state(BlackjackState::StartScreen)
         .add_system(blackjack_control_system)
         .add_system(update_value_text_system)
+        .add_system(update_table_rules_text_system)
+        .add_system(update_discard_tray_system)
+        .add_system(update_side_bet_text_system)
+        .add_system(update_trainer_text_system)
+        .add_system(update_count_text_system)
+        .add_system(update_seat_text_system)
+        .add_system(bot_system)
+        .add_system(close_training_system)
+        .add_system_set(
+            SystemSet::on_enter(BlackjackState::StartScreen)
+            .with_system(end_training_system))
+        .add_system_set(
+            SystemSet::on_update(BlackjackState::StartScreen)
+            .with_system(update_control_guide_system))
+        .add_system_set(
+            SystemSet::on_exit(BlackjackState::StartScreen)
+            .with_system(apply_profile_system))
+        .add_system_set(
+            SystemSet::on_enter(BlackjackState::PreGame)
+            .with_system(check_bankroll_system))
         .add_system_set(
             SystemSet::on_update(BlackjackState::PreGame)
             .with_system(pre_game_system)
             .with_system(update_control_guide_system))
+        .add_system_set(
+            SystemSet::on_update(BlackjackState::GameOver)
+            .with_system(game_over_system)
+            .with_system(update_control_guide_system))
         .add_system_set(
             SystemSet::on_update(BlackjackState::ChangeBet)
             .with_system(update_control_guide_system))
         .add_system_set(
-            SystemSet::on_update(BlackjackState::InitialDraw)
-                .with_system(initial_draw_system)
+            SystemSet::on_update(BlackjackState::Dealing)
+                .with_system(draw_system)
                 .with_system(update_control_guide_system))
         .add_system_set(
-            SystemSet::on_update(BlackjackState::PlayerDraw)
-                .with_system(draw_system)
+            SystemSet::on_update(BlackjackState::EarlySurrender)
                 .with_system(update_control_guide_system))
         .add_system_set(
-            SystemSet::on_update(BlackjackState::DealerDraw)
-                .with_system(draw_system)
+            SystemSet::on_update(BlackjackState::Insurance)
                 .with_system(update_control_guide_system))
         .add_system_set(
-            SystemSet::on_update(BlackjackState::PlayerTurn)
-                .with_system(player_turn_system)
+            SystemSet::on_update(BlackjackState::EvenMoney)
                 .with_system(update_control_guide_system))
         .add_system_set(
-            SystemSet::on_update(BlackjackState::DealerTurn)
-                .with_system(dealer_turn_system)
+            SystemSet::on_update(BlackjackState::PlayerTurn)
                 .with_system(update_control_guide_system))
         .add_system_set(
             SystemSet::on_update(BlackjackState::GameEnd)
@@ -43,6 +72,9 @@ impl Plugin for BlackjackPlugin {
         .add_system_set(
             SystemSet::on_enter(BlackjackState::GameEnd)
                 .with_system(game_end_system))
+        .add_system_set(
+            SystemSet::on_update(BlackjackState::CountQuiz)
+                .with_system(update_control_guide_system))
         .add_system_set(
             SystemSet::on_update(BlackjackState::CleanUp)
                 .with_system(clean_up_system)
@@ -50,23 +82,57 @@ impl Plugin for BlackjackPlugin {
     }
 }
 
-pub const CARD_SHIFT: f32 = 50.0;
+// The table rules as a resource, the rules themselves are shared with the simulator and don't know about Bevy
+#[derive(Resource)]
+pub struct Rules(pub TableRules);
+
+impl Deref for Rules {
+    type Target = TableRules;
 
-pub const BET_STEP: f32 = 1.0;
-pub const MIN_BET: f32 = 1.0;
-pub const MAX_BET: f32 = 10.0;
+    fn deref(&self) -> &TableRules {
+        &self.0
+    }
+}
 
-#[derive(Copy, Clone)]
-pub struct PlayingCard {
-    suit: CardSuit,
-    value: u8,
+// The rules file is read from the assets folder, like the textures and sounds
+fn load_rules() -> Rules {
+    let path = bevy::asset::FileAssetIo::get_base_path().join("assets").join(rules::RULES_FILE);
+    Rules(rules::load_table_rules(&path))
 }
 
+pub const CARD_SHIFT: f32 = 50.0;
+pub const HAND_SHIFT: f32 = 300.0;
+// With more than one seat the seats share the table from SEAT_AREA_X onwards, right of the controls guide
+pub const SEAT_AREA_X: f32 = -500.0;
+pub const SEAT_AREA: f32 = 1550.0;
+// Seconds a computer player or autoplay takes over each decision, so that its play can be followed
+pub const AI_DELAY: f32 = 0.8;
+// Counting practice asks for the count after this many rounds
+pub const QUIZ_EVERY: u32 = 3;
+
 #[derive(Component)]
 struct Card;
 
+// The dealer's face-down card, flipped when the dealer's turn starts
+#[derive(Component)]
+struct HoleCard;
+
+// Marks the players' card sprites with the seat and the index of the hand they belong to
 #[derive(Component)]
-struct UiPlayerHandValue;
+struct PlayerCard {
+    seat: usize,
+    hand: usize,
+}
+
+#[derive(Component)]
+struct UiPlayerHandValue {
+    seat: usize,
+    hand: usize,
+}
+
+// Name and money above each seat, only shown when there's more than one seat
+#[derive(Component)]
+struct UiSeat(usize);
 
 #[derive(Component)]
 struct UiDealerHandValue;
@@ -74,85 +140,265 @@ struct UiDealerHandValue;
 #[derive(Component)]
 struct Prop;
 
+#[derive(Component)]
+struct DiscardTray;
+
 #[derive(Component)]
 pub struct UiBlackjackControlsGuide;
 
+#[derive(Component)]
+struct UiTableRules;
+
+#[derive(Component)]
+struct UiSideBetResults;
+
+#[derive(Component)]
+struct UiTrainer;
+
+#[derive(Component)]
+struct UiCount;
+
+// The round is played out by the engine as soon as the player decides, its events are then drawn on the table one card at a time
 #[derive(Resource)]
 struct CardPiles {
-    deck: Vec<PlayingCard>,
-    player_hand: Vec<PlayingCard>,
-    dealer_hand: Vec<PlayingCard>,
+    shoe: Shoe,
+    round: Option<Round>,
+    events: VecDeque<Event>,
+    hole_card_hidden: bool,
+}
+
+// The wager that Z and X change during ChangeBet, for the current player
+#[derive(Resource)]
+struct SelectedWager(Wager);
+
+// Basic strategy for the table rules, shown with the controls during the player's turn when the profile asks for advice
+#[derive(Resource)]
+struct StrategyAdvisor {
+    strategy: BasicStrategy,
+}
+
+// Grades every playing decision against basic strategy while turned on with T. The report is printed when it's turned
+// off, when the profile leaves the table and when the window is closed.
+#[derive(Resource, Default)]
+struct TrainingSession {
+    active: bool,
+    trainer: Trainer,
+    last_decision: Option<Decision>,
+}
+
+// Counting practice, turned on with K. The count is kept from the start of every shoe even while practice is off.
+#[derive(Resource)]
+struct CountingPractice {
+    active: bool,
+    counter: CardCounter,
+    rounds: u32, // since the last quiz
+    guess: i32,
+    asked: u32,
+    right: u32,
+    reveal: bool, // debug builds can show the count live with R
+}
+
+// Side bet outcomes of the current round, shown under the player's money
+#[derive(Resource)]
+struct SideBetResults(Vec<String>);
+
+// The seats, the round being played on the shoe and the rules it's played by, which every system moving the game along
+// needs together
+#[derive(SystemParam)]
+struct Table<'w, 's> {
+    players: ResMut<'w, Players>,
+    card_piles: ResMut<'w, CardPiles>,
+    game_rng: ResMut<'w, GameRng>,
+    rules: Res<'w, Rules>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+// Where the next card goes on the felt, its sprite and the sound of it being dealt
+#[derive(SystemParam)]
+struct CardSprites<'w, 's> {
+    layout: ResMut<'w, TableLayout>,
+    asset_server: Res<'w, AssetServer>,
+    game_textures: Res<'w, GameTextures>,
+    audio: Res<'w, Audio>,
+    sound: Res<'w, SFXPlayCard>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+// The cards already on the felt that move when a hand is split or turn over when the hole card is revealed
+#[derive(SystemParam)]
+struct DealtCards<'w, 's> {
+    player_cards: Query<'w, 's, (&'static mut Transform, &'static mut PlayerCard)>,
+    hole_cards: Query<'w, 's, (Entity, &'static mut TextureAtlasSprite), With<HoleCard>>,
+}
+
+// The help the keyboard turns on and off: strategy advice, the trainer and counting practice
+#[derive(SystemParam)]
+struct Practice<'w, 's> {
+    advisor: Res<'w, StrategyAdvisor>,
+    training: ResMut<'w, TrainingSession>,
+    counting: ResMut<'w, CountingPractice>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
 }
 
+// Counts down a computer player's decision
 #[derive(Resource)]
-struct Coordinates {
-    player_deal_pos_x: f32,
-    player_deal_pos_z: f32,
+struct AiPace(Timer);
+
+// Plays the human seats with basic strategy while turned on with A, starting and clearing rounds on its own
+#[derive(Resource)]
+struct Autoplay {
+    active: bool,
+    strategy: Box<dyn Strategy>,
+}
+
+// Where the next card of every hand goes
+#[derive(Resource)]
+struct TableLayout {
+    seats: Vec<SeatLayout>,
     dealer_deal_pos_x: f32,
     dealer_deal_pos_z: f32,
 }
 
-#[derive(Copy, Clone)]
-enum CardSuit {
-    Heart,
-    Diamond,
-    Spade,
-    Club,
+// A seat's own deal coordinates, split hands are laid out to the right of the first hand
+struct SeatLayout {
+    x: f32, // the seat's first card
+    hand_shift: f32,
+    value_offset: Vec2, // top left of a hand's value text from its first card
+    deal_pos_x: Vec<f32>,
+    deal_pos_z: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wager {
+    Bet,
+    SideBet(usize), // index into TableRules::side_bets
 }
 
+// The game starts on the start screen, where a profile is picked, and goes back there with Esc between rounds. It's
+// over when the profile's bankroll can't cover the minimum bet, until the table bails the player out or lends money.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
-enum BlackjackState {
+pub enum BlackjackState {
+    StartScreen,
     PreGame,
+    GameOver,
     ChangeBet,
-    InitialDraw,
+    Dealing,
+    EarlySurrender,
+    Insurance,
+    EvenMoney,
     PlayerTurn,
-    PlayerDraw,
-    DealerTurn,
-    DealerDraw,
     GameEnd,
+    CountQuiz,
     CleanUp,
 }
 
+pub struct BlackjackPlugin;
+
+impl Wager {
+    // Cycles through the main bet and every side bet on the table
+    fn next(&self, rules: &TableRules) -> Wager {
+        let next = match self {
+            Wager::Bet => 0,
+            Wager::SideBet(i) => i + 1,
+        };
+        if next < rules.side_bets.len() {
+            Wager::SideBet(next)
+        } else {
+            Wager::Bet
+        }
+    }
 
-//to_string for CardSuit
-impl fmt::Display for CardSuit {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    fn name(&self, rules: &TableRules) -> String {
         match self {
-            CardSuit::Heart => write!(f, "Heart"),
-            CardSuit::Diamond => write!(f, "Diamond"),
-            CardSuit::Spade => write!(f, "Spade"),
-            CardSuit::Club => write!(f, "Club"),
+            Wager::Bet => "Bet".to_string(),
+            Wager::SideBet(i) => rules.side_bets[*i].name.clone(),
         }
     }
 }
-pub struct BlackjackPlugin;
 
+impl SeatLayout {
+    // A lone seat keeps the middle of the table, more seats split the seat area evenly
+    fn new(seat: usize, seats: usize) -> SeatLayout {
+        let mut layout = if seats == 1 {
+            SeatLayout { x: 0.0, hand_shift: HAND_SHIFT, value_offset: Vec2::new(-150.0, -500.0), deal_pos_x: Vec::new(), deal_pos_z: Vec::new() }
+        } else {
+            let width = SEAT_AREA / seats as f32;
+            SeatLayout {
+                x: SEAT_AREA_X + seat as f32 * width,
+                hand_shift: (width / 2.0).min(HAND_SHIFT),
+                value_offset: Vec2::new(-80.0, -440.0),
+                deal_pos_x: Vec::new(),
+                deal_pos_z: Vec::new(),
+            }
+        };
+        layout.reset();
+        layout
+    }
+
+    fn reset(&mut self) {
+        self.deal_pos_x = vec![self.x];
+        self.deal_pos_z = vec![1.0];
+    }
+
+    fn add_hand(&mut self, hand: usize) {
+        self.deal_pos_x.push(self.x + hand as f32 * self.hand_shift);
+        self.deal_pos_z.push(1.0);
+    }
+
+    fn value_pos(&self, hand: usize) -> Vec3 {
+        Vec3::new(self.x + hand as f32 * self.hand_shift + self.value_offset.x, self.value_offset.y, 100.0)
+    }
+}
+
+impl TableLayout {
+    fn new(seats: usize) -> TableLayout {
+        TableLayout {
+            seats: (0..seats).map(|seat| SeatLayout::new(seat, seats)).collect(),
+            dealer_deal_pos_x: 50.0,
+            dealer_deal_pos_z: 1.0,
+        }
+    }
+}
 
 fn setup_system(
 	mut commands: Commands,
     asset_server: Res<AssetServer>,
     game_textures: Res<GameTextures>,
+    rules: Res<Rules>,
+    mut game_rng: ResMut<GameRng>,
 ) {
-    let deck = init_deck();
-    let  dealer_hand: Vec<PlayingCard> = Vec::new();
-    let player_hand: Vec<PlayingCard> = Vec::new();
+    let shoe = init_shoe(rules.decks, rules.penetration, &mut game_rng.rng);
 
     let card_piles = CardPiles {
-        deck: deck,
-        player_hand: player_hand,
-        dealer_hand: dealer_hand,
+        shoe,
+        round: None,
+        events: VecDeque::new(),
+        hole_card_hidden: false,
     };
 
     commands.insert_resource(card_piles);
+    commands.insert_resource(SelectedWager(Wager::Bet));
+    commands.insert_resource(StrategyAdvisor { strategy: BasicStrategy::new(&rules) });
+    commands.insert_resource(SideBetResults(Vec::new()));
+    commands.insert_resource(TrainingSession::default());
+    commands.insert_resource(CountingPractice {
+        active: false,
+        counter: CardCounter::new(CountingSystem::HiLo, rules.decks),
+        rounds: 0,
+        guess: 0,
+        asked: 0,
+        right: 0,
+        reveal: false,
+    });
 
-    // Set player hand deal location
-    let cords = Coordinates {
-        player_deal_pos_x: 0.0,
-        player_deal_pos_z: 1.0,
-        dealer_deal_pos_x: 50.0,
-        dealer_deal_pos_z: 1.0,
-    };
-    commands.insert_resource(cords);
+    commands.insert_resource(AiPace(Timer::from_seconds(AI_DELAY, TimerMode::Once)));
+    commands.insert_resource(Autoplay { active: false, strategy: Box::new(strategy::Basic::new(&rules)) });
+
+    // Set every seat's hand deal location
+    let layout = TableLayout::new(rules.seats.len());
 
     let text_style = TextStyle {
             font: asset_server.load("retro_gaming.ttf"),
@@ -172,18 +418,54 @@ fn setup_system(
         ..Default::default()
     }).insert(Prop);
 
+    // Spawn discard tray prop, hidden until the first round has been cleaned up
+    commands.spawn(SpriteSheetBundle {
+        sprite: TextureAtlasSprite {
+            index: 53,
+            flip_x: true,
+            ..Default::default()
+        },
+        texture_atlas: game_textures.card_sheet.clone(),
+        transform: Transform {
+        translation: Vec3::new(450.0, 250.0, 1.0),
+        scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.0),
+        ..Default::default()
+        },
+        visibility: Visibility::INVISIBLE,
+        ..Default::default()
+    }).insert(Prop).insert(DiscardTray);
 
-    // Spawn hand value indicator textbundles
-    commands
-        .spawn(Text2dBundle {
-            transform: Transform {
-                translation: Vec3::new(-150.0, -500.0, 100.0),
+
+    // Spawn hand value indicator textbundles, and the seat names when there's more than one seat
+    for (seat, seat_layout) in layout.seats.iter().enumerate() {
+        commands
+            .spawn(Text2dBundle {
+                transform: Transform {
+                    translation: seat_layout.value_pos(0),
+                    ..default()
+                },
+                text: Text::from_section("21", text_style.clone()),
                 ..default()
-            },
-            text: Text::from_section("21", text_style.clone()),
-            ..default()
-        })
-        .insert(UiPlayerHandValue);
+            })
+            .insert(UiPlayerHandValue { seat, hand: 0 });
+        if layout.seats.len() > 1 {
+            commands
+                .spawn(Text2dBundle {
+                    transform: Transform {
+                        translation: Vec3::new(seat_layout.x - 80.0, -395.0, 100.0),
+                        ..default()
+                    },
+                    text: Text::from_section("", TextStyle {
+                        font: asset_server.load("retro_gaming.ttf"),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    }),
+                    ..default()
+                })
+                .insert(UiSeat(seat));
+        }
+    }
+    commands.insert_resource(layout);
     
         commands
     .spawn(Text2dBundle {
@@ -195,6 +477,66 @@ fn setup_system(
         ..default()
     })
     .insert(UiDealerHandValue);
+
+    // Table rules printed on the felt
+    commands
+    .spawn(Text2dBundle {
+        transform: Transform {
+            translation: Vec3::new(0.0, 0.0, 100.0),
+            ..default()
+        },
+        text: Text::from_section("", TextStyle {
+                font: asset_server.load("retro_gaming.ttf"),
+                font_size: 40.0,
+                color: Color::GOLD,
+            }),
+        ..default()
+    }).insert(UiTableRules);
+
+    // Side bet results
+    commands
+    .spawn(Text2dBundle {
+        transform: Transform {
+            translation: Vec3::new(-1100.0, 540.0, 100.0),
+            ..default()
+        },
+        text: Text::from_section("", TextStyle {
+                font: asset_server.load("retro_gaming.ttf"),
+                font_size: 40.0,
+                color: Color::GOLD,
+            }),
+        ..default()
+    }).insert(UiSideBetResults);
+
+    // Trainer score and feedback
+    commands
+    .spawn(Text2dBundle {
+        transform: Transform {
+            translation: Vec3::new(0.0, 600.0, 100.0),
+            ..default()
+        },
+        text: Text::from_section("", TextStyle {
+                font: asset_server.load("retro_gaming.ttf"),
+                font_size: 30.0,
+                color: Color::WHITE,
+            }),
+        ..default()
+    }).insert(UiTrainer);
+
+    // Counting quiz and the live count of debug builds
+    commands
+    .spawn(Text2dBundle {
+        transform: Transform {
+            translation: Vec3::new(0.0, 500.0, 100.0),
+            ..default()
+        },
+        text: Text::from_section("", TextStyle {
+                font: asset_server.load("retro_gaming.ttf"),
+                font_size: 30.0,
+                color: Color::CYAN,
+            }),
+        ..default()
+    }).insert(UiCount);
     
     // Controls Guide
     commands
@@ -216,344 +558,984 @@ fn setup_system(
 fn pre_game_system(
     mut keyboard: ResMut<Input<KeyCode>>,
     mut blackjack_state: ResMut<State<BlackjackState>>,
+    autoplay: Res<Autoplay>,
+    mut players: ResMut<Players>,
 ) {
-    if keyboard.just_pressed(KeyCode::Z) { // Start the game, add conditions about having enough money for bet
-        keyboard.clear_just_pressed(KeyCode::Z);
-        blackjack_state.set(BlackjackState::InitialDraw).unwrap();
-    } else if keyboard.just_pressed(KeyCode::C) { // Change Bet
+    // Starting the game is handled in blackjack_control_system, which also takes the bet
+    if keyboard.just_pressed(KeyCode::C) && !autoplay.active { // Change Bet
         keyboard.clear_just_pressed(KeyCode::C);
         blackjack_state.set(BlackjackState::ChangeBet).unwrap();
+    } else if keyboard.just_pressed(KeyCode::Escape) && !autoplay.active { // Back to the profiles
+        keyboard.clear_just_pressed(KeyCode::Escape);
+        save_profile(&mut players); // with the bet and preferences changed since the last round
+        blackjack_state.set(BlackjackState::StartScreen).unwrap();
     }
 }
 
-fn initial_draw_system(
-
-    card_piles: ResMut<CardPiles>,
+// Before every round a human whose bet is more than is left bets what they still can, and the game is over once the
+// profile's seat can't cover the minimum bet
+fn check_bankroll_system(
+    mut players: ResMut<Players>,
+    mut autoplay: ResMut<Autoplay>,
     mut blackjack_state: ResMut<State<BlackjackState>>,
+    rules: Res<Rules>,
 ) {
-    if card_piles.player_hand.len() == 2 {
-        if card_piles.dealer_hand.len() == 2 {
-                blackjack_state.set(BlackjackState::PlayerTurn).unwrap();
-            } else {
-                blackjack_state.set(BlackjackState::DealerDraw).unwrap();
+    for player in players.seats.iter_mut().filter(|player| player.strategy.is_none()) {
+        if player.profile.money >= rules.min_bet && round_cost(player) > player.profile.money {
+            for side_bet in player.side_bets.iter_mut() {
+                *side_bet = Money::ZERO;
             }
-        } else {
-            blackjack_state.set(BlackjackState::PlayerDraw).unwrap();
+            let steps = (player.profile.money - rules.min_bet).steps(rules.bet_step);
+            player.profile.bet = player.profile.bet.min(rules.min_bet + rules.bet_step * steps);
+            println!("{} can't cover the last bet, betting {}", player.profile.name, player.profile.bet);
         }
-}
+    }
 
-fn player_turn_system(
-    card_piles: ResMut<CardPiles>,
-    mut blackjack_state: ResMut<State<BlackjackState>>,
-) {
-    if card_piles.player_hand.len() == 2 && hand_value(&card_piles.player_hand) == 21 {
-        println!("Player blackjack!");
-        blackjack_state.set(BlackjackState::DealerTurn).unwrap();
-    } else if hand_value(&card_piles.player_hand) > 21 { 
-        println!("Player bust!");
-        blackjack_state.set(BlackjackState::GameEnd).unwrap();
+    let owner = match players.owner {
+        Some(owner) if players.seats[owner].profile.money < rules.min_bet => owner,
+        _ => return,
+    };
+    players.current = owner;
+    if autoplay.active {
+        autoplay.active = false;
+        println!("Autoplay off");
     }
+    println!("Game over! {} has {} left and the minimum bet is {}", players.seats[owner].profile.name, players.seats[owner].profile.money, rules.min_bet);
+    // PreGame is still being entered, set() would refuse to queue another state
+    blackjack_state.overwrite_set(BlackjackState::GameOver).unwrap();
 }
 
-fn dealer_turn_system(
-    card_piles: ResMut<CardPiles>,
+// Whether the table still lends to the player, there's one loan at a time
+fn loan_offered(player: &Player, rules: &TableRules) -> bool {
+    rules.game_over.loan.is_positive() && !player.profile.debt.is_positive()
+}
+
+// A bailout or a loan puts the profile back at the table, Esc goes back to the profiles with the game still over
+fn game_over_system(
+    mut keyboard: ResMut<Input<KeyCode>>,
     mut blackjack_state: ResMut<State<BlackjackState>>,
+    mut players: ResMut<Players>,
+    rules: Res<Rules>,
 ) {
-    if card_piles.dealer_hand.len() == 3 && hand_value(&card_piles.dealer_hand) == 21 { 
-        println!("Dealer blackjack!");
-        blackjack_state.set(BlackjackState::GameEnd).unwrap();
-    } else if hand_value(&card_piles.dealer_hand) > 21 {
-        println!("Dealer bust!");
-        blackjack_state.set(BlackjackState::GameEnd).unwrap();
-    } else if hand_value(&card_piles.dealer_hand) >= 17 { 
-        println!("Dealer stands");
-        blackjack_state.set(BlackjackState::GameEnd).unwrap();
+    let owner = match players.owner {
+        Some(owner) => owner,
+        None => return,
+    };
+    let player = &mut players.seats[owner];
+    if keyboard.just_pressed(KeyCode::Z) && rules.game_over.bailout.is_positive() { // Bailout
+        keyboard.clear_just_pressed(KeyCode::Z);
+        player.profile.money += rules.game_over.bailout;
+        player.profile.stats.bailouts += 1;
+        println!("{} is bailed out with {}", player.profile.name, rules.game_over.bailout);
+    } else if keyboard.just_pressed(KeyCode::X) && loan_offered(player, &rules) { // Loan
+        keyboard.clear_just_pressed(KeyCode::X);
+        player.profile.money += rules.game_over.loan;
+        player.profile.debt += rules.game_over.loan_debt();
+        player.profile.stats.loans += 1;
+        println!("{} borrows {} and owes {}, {}% of every win pays it back", player.profile.name, rules.game_over.loan, player.profile.debt, rules.game_over.repay_percent);
+    } else if keyboard.just_pressed(KeyCode::Escape) { // Back to the profiles
+        keyboard.clear_just_pressed(KeyCode::Escape);
+        save_profile(&mut players);
+        blackjack_state.set(BlackjackState::StartScreen).unwrap();
+        return;
     } else {
-        println!("Dealer hits");
-        blackjack_state.set(BlackjackState::DealerDraw).unwrap();
+        return;
     }
+    save_profile(&mut players);
+    blackjack_state.set(BlackjackState::PreGame).unwrap();
 }
 
+// Draws the round's events on the table, one card per frame, then waits for whatever the round needs next
 fn draw_system(
     mut commands: Commands,
-    mut cords: ResMut<Coordinates>,
-    mut card_piles: ResMut<CardPiles>,
-    audio: Res<Audio>,
-    sound: Res<SFXPlayCard>,
-    game_textures: Res<GameTextures>,
+    CardSprites { mut layout, asset_server, game_textures, audio, sound, .. }: CardSprites,
+    Table { mut players, mut card_piles, rules, .. }: Table,
+    DealtCards { mut player_cards, mut hole_cards }: DealtCards,
+    mut side_bet_results: ResMut<SideBetResults>,
+    mut counting: ResMut<CountingPractice>,
     mut blackjack_state: ResMut<State<BlackjackState>>,
 ) {
-    audio.play(sound.0.clone());
-    let card: PlayingCard = get_card(&mut card_piles.deck);
-    match blackjack_state.current() {
-        BlackjackState::PreGame => panic!("Should not call this function in this state!"),
-        BlackjackState::ChangeBet => panic!("Should not call this function in this state!"),
-        BlackjackState::InitialDraw => panic!("Should not call this function in this state!"),
-        BlackjackState::PlayerTurn => panic!("Should not call this function in this state!"),
-        BlackjackState::DealerTurn => panic!("Should not call this function in this state!"),
-        BlackjackState::CleanUp => panic!("Should not call this function in this state!"),
-        BlackjackState::GameEnd => panic!("Should not call this function in this state!"),
-        BlackjackState::PlayerDraw => {
-            commands.spawn(SpriteSheetBundle {
-                sprite: TextureAtlasSprite::new(card_to_asset_index(&card)),
-                texture_atlas: game_textures.card_sheet.clone(),
-                transform: Transform {
-                translation: Vec3::new(cords.player_deal_pos_x, -570.0, cords.player_deal_pos_z),
-                scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.0),
-                ..Default::default()
-                },
-                ..Default::default()
-            }).insert(Card);
-            cords.player_deal_pos_x += CARD_SHIFT;
-            cords.player_deal_pos_z += 1.0;
-            card_piles.player_hand.push(card);
-            if card_piles.player_hand.len() + card_piles.dealer_hand.len() < 5 { blackjack_state.set(BlackjackState::InitialDraw).unwrap(); } else { blackjack_state.set(BlackjackState::PlayerTurn).unwrap(); }
-        },
-        BlackjackState::DealerDraw => {
-            let card: PlayingCard = if card_piles.dealer_hand.len() == 1 {
-                PlayingCard{ suit: CardSuit::Heart, value: 0 } // dummy card
-            } else {
-                get_card(&mut card_piles.deck)
-            };
+    while let Some(event) = card_piles.events.pop_front() {
+        let message = event_message(&event, &card_piles, &players, &rules);
+        if let Some(message) = &message {
+            println!("{}", message);
+        }
+        match event {
+            Event::PlayerCard { seat, hand, card } => {
+                audio.play(sound.0.clone());
+                counting.counter.see(card);
+                let seat_layout = &mut layout.seats[seat];
+                commands.spawn(SpriteSheetBundle {
+                    sprite: TextureAtlasSprite::new(card_to_asset_index(&card)),
+                    texture_atlas: game_textures.card_sheet.clone(),
+                    transform: Transform {
+                    translation: Vec3::new(seat_layout.deal_pos_x[hand], -570.0, seat_layout.deal_pos_z[hand]),
+                    scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.0),
+                    ..Default::default()
+                    },
+                    ..Default::default()
+                }).insert(Card).insert(PlayerCard { seat, hand });
+                seat_layout.deal_pos_x[hand] += CARD_SHIFT;
+                seat_layout.deal_pos_z[hand] += 1.0;
+                return;
+            },
+            Event::DealerCard { card, face_down } => {
+                audio.play(sound.0.clone());
+                let asset_index: usize = if face_down {
+                    52
+                } else {
+                    card_to_asset_index(&card)
+                };
 
-            let asset_index: usize = if card_piles.dealer_hand.len() == 1 {
-                52
-            } else {
-                card_to_asset_index(&card)
-            };
+                let mut entity = commands.spawn(SpriteSheetBundle {
+                    sprite: TextureAtlasSprite::new(asset_index),
+                    texture_atlas: game_textures.card_sheet.clone(),
+                    transform: Transform {
+                    translation: Vec3::new(layout.dealer_deal_pos_x, -270.0, layout.dealer_deal_pos_z),
+                    scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.0),
+                    ..Default::default()
+                    },
+                    ..Default::default()
+                });
+                entity.insert(Card);
+                if face_down {
+                    entity.insert(HoleCard);
+                    card_piles.hole_card_hidden = true;
+                } else {
+                    counting.counter.see(card);
+                }
+                layout.dealer_deal_pos_x += CARD_SHIFT;
+                layout.dealer_deal_pos_z += 1.0;
+                return;
+            },
+            Event::HoleCardRevealed(card) => {
+                counting.counter.see(card);
+                for (entity, mut sprite) in hole_cards.iter_mut() {
+                    sprite.index = card_to_asset_index(&card);
+                    commands.entity(entity).remove::<HoleCard>();
+                }
+                card_piles.hole_card_hidden = false;
+            },
+            Event::HandSplit { seat, hand, new_hand } => {
+                // Move the top card of the split hand to the start of its own row
+                let seat_layout = &mut layout.seats[seat];
+                seat_layout.add_hand(new_hand);
+                let top_card = player_cards
+                    .iter_mut()
+                    .filter(|(_, player_card)| player_card.seat == seat && player_card.hand == hand)
+                    .max_by(|a, b| a.0.translation.z.partial_cmp(&b.0.translation.z).unwrap());
+                if let Some((mut transform, mut player_card)) = top_card {
+                    transform.translation.x = seat_layout.deal_pos_x[new_hand];
+                    transform.translation.z = seat_layout.deal_pos_z[new_hand];
+                    player_card.hand = new_hand;
+                }
+                seat_layout.deal_pos_x[hand] -= CARD_SHIFT;
+                seat_layout.deal_pos_z[hand] -= 1.0;
+                seat_layout.deal_pos_x[new_hand] += CARD_SHIFT;
+                seat_layout.deal_pos_z[new_hand] += 1.0;
 
-            commands.spawn(SpriteSheetBundle {
-                sprite: TextureAtlasSprite::new(asset_index),
-                texture_atlas: game_textures.card_sheet.clone(),
-                transform: Transform {
-                translation: Vec3::new(cords.dealer_deal_pos_x, -270.0, cords.dealer_deal_pos_z),
-                scale: Vec3::new(SPRITE_SCALE, SPRITE_SCALE, 1.0),
-                ..Default::default()
-                },
-                ..Default::default()
-            }).insert(Card);
-            cords.dealer_deal_pos_x += CARD_SHIFT;
-            cords.dealer_deal_pos_z += 1.0;
-            card_piles.dealer_hand.push(card);
-            // if dummy card was played, move coordinates so the next real card will be played on top of it
-            if card_piles.dealer_hand.len() == 2 { cords.dealer_deal_pos_x -= CARD_SHIFT;}
-            if card_piles.player_hand.len() + card_piles.dealer_hand.len() < 5 { blackjack_state.set(BlackjackState::InitialDraw).unwrap(); } else { blackjack_state.set(BlackjackState::DealerTurn).unwrap(); }
+                commands
+                    .spawn(Text2dBundle {
+                        transform: Transform {
+                            translation: seat_layout.value_pos(new_hand),
+                            ..default()
+                        },
+                        text: Text::from_section("", TextStyle {
+                            font: asset_server.load("retro_gaming.ttf"),
+                            font_size: 50.0,
+                            color: Color::WHITE,
+                        }),
+                        ..default()
+                    })
+                    .insert(UiPlayerHandValue { seat, hand: new_hand });
+            },
+            Event::SideBetSettled { .. } => side_bet_results.0.extend(message),
+            Event::DiscardsReshuffled => counting.counter.reset(), // the discards are back in play, counting starts over
+            Event::RoundOver(settlements) => {
+                for (player, settlement) in players.seats.iter_mut().zip(settlements.iter()) {
+                    if !settlement.outcomes.is_empty() {
+                        player.history.push(settlement.net());
+                        let repaid = rules.game_over.repayment(settlement.net(), player.profile.debt);
+                        if repaid.is_positive() {
+                            player.profile.money -= repaid;
+                            player.profile.debt -= repaid;
+                            println!("{} pays back {} of the loan, {} to go", player.profile.name, repaid, player.profile.debt);
+                        }
+                        player.profile.record(settlement);
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
 
-        },
+    // The keyboard follows the seat the round is waiting on
+    let (phase, active_seat) = match &card_piles.round {
+        Some(round) => (Some(round.phase), round.active_seat),
+        None => (None, 0),
+    };
+    match phase {
+        Some(Phase::Finished) | None => players.current = players.first_human(),
+        _ => players.current = active_seat,
     }
+    match phase {
+        Some(Phase::EarlySurrender) => blackjack_state.set(BlackjackState::EarlySurrender).unwrap(),
+        Some(Phase::Insurance) => blackjack_state.set(BlackjackState::Insurance).unwrap(),
+        Some(Phase::EvenMoney) => blackjack_state.set(BlackjackState::EvenMoney).unwrap(),
+        Some(Phase::PlayerTurn) => blackjack_state.set(BlackjackState::PlayerTurn).unwrap(),
+        Some(Phase::Finished) | None => blackjack_state.set(BlackjackState::GameEnd).unwrap(),
+    }
+}
+
+// What gets written to the console for each thing that happens at the table
+fn event_message(event: &Event, card_piles: &CardPiles, players: &Players, rules: &TableRules) -> Option<String> {
+    // With more than one seat every message about a seat says whose it is
+    let seat_name = |seat: &usize| {
+        if players.seats.len() > 1 {
+            format!("{}: ", players.seats[*seat].profile.name)
+        } else {
+            String::new()
+        }
+    };
+    let message = match event {
+        Event::PlayerCard { .. } | Event::DealerCard { .. } | Event::HoleCardRevealed(_) | Event::HandSplit { .. } => return None,
+        Event::DealerChecks => "Dealer checks for blackjack".to_string(),
+        Event::DealerBlackjack => "Dealer blackjack!".to_string(),
+        Event::PlayerBlackjack { seat, .. } => format!("{} blackjack!", players.seats[*seat].profile.name),
+        Event::PlayerBust { seat, .. } => format!("{} bust!", players.seats[*seat].profile.name),
+        Event::DealerHits => "Dealer hits".to_string(),
+        Event::DealerStands => "Dealer stands".to_string(),
+        Event::DealerBust => "Dealer bust!".to_string(),
+        Event::SideBetSettled { seat, side_bet, line, .. } => {
+            let side_bet = &rules.side_bets[*side_bet];
+            match line {
+                Some(line) => format!("{}{}: {} pays {} to 1", seat_name(seat), side_bet.name, side_bet.paytable[*line].name, side_bet.paytable[*line].pays),
+                None => format!("{}{} lost", seat_name(seat), side_bet.name),
+            }
+        },
+        Event::HandSettled { seat, hand, outcome, .. } => {
+            let result = match outcome {
+                Outcome::Win | Outcome::Blackjack => "You win!",
+                Outcome::Push => "Draw!",
+                Outcome::Lose => "You lose!",
+                Outcome::Surrendered => "Surrendered!",
+                Outcome::EvenMoney => "Even money!",
+            };
+            match &card_piles.round {
+                Some(round) if round.seats[*seat].hands.len() > 1 => format!("{}Hand {}: {}", seat_name(seat), hand + 1, result),
+                _ => format!("{}{}", seat_name(seat), result),
+            }
+        },
+        Event::InsuranceSettled { seat, returned } => {
+            if returned.is_positive() {
+                format!("{}Insurance pays {}!", seat_name(seat), returned)
+            } else {
+                format!("{}Insurance lost!", seat_name(seat))
+            }
+        },
+        Event::CutCardReached => "Cut card reached, shuffling after this round".to_string(),
+        Event::DiscardsReshuffled => "Shoe is empty, shuffling the discards back in".to_string(),
+        Event::RoundOver(settlements) => {
+            let mut message = String::from("Round over");
+            for (seat, settlement) in settlements.iter().enumerate() {
+                if !settlement.outcomes.is_empty() {
+                    message.push_str(&format!(", {}net {:+}", seat_name(&seat), settlement.net()));
+                }
+            }
+            message
+        },
+    };
+    Some(message)
 }
 
+// The engine has already settled the round, the insurance wagers are only shown until then. The profile is saved
+// after every round.
 fn game_end_system(
-    card_piles: ResMut<CardPiles>,
-    mut player: ResMut<Player>,
+    mut players: ResMut<Players>,
 ) {
-    if hand_value(&card_piles.player_hand) > 21 {
-        println!("You lose!"); //Player bust
-    } else if hand_value(&card_piles.dealer_hand) > 21 {
-        println!("You win!"); //Dealer bust
-        player.money += player.bet * 2.0;
-    } else if hand_value(&card_piles.player_hand) == 21 && card_piles.player_hand.len() == 2 {
-        if hand_value(&card_piles.dealer_hand) == 21 && card_piles.dealer_hand.len() == 3 {
-            println!("Draw!"); //Dealer & Player blacjack
-            player.money += player.bet;
-        } else {
-            println!("You win!"); //Player blacjack, x1.5 payout
-            player.money += player.bet * 3.0;
-        }
-    } else if hand_value(&card_piles.dealer_hand) == 21 && card_piles.dealer_hand.len() == 3 {
-        println!("You lose!"); //Dealer blackjack
-    } else if hand_value(&card_piles.player_hand) == hand_value(&card_piles.dealer_hand) {
-        println!("Draw!"); //Dealer and Player have same hand value
-        player.money += player.bet;
-    } else if hand_value(&card_piles.player_hand) > hand_value(&card_piles.dealer_hand) {
-        println!("You win"); //Player hand is better than Dealer's
-        player.money += player.bet * 2.0;
-    } else {
-        println!("You lose!") //Player hand is worse than Dealer's
+    for player in players.seats.iter_mut() {
+        player.insurance = Money::ZERO;
+    }
+    save_profile(&mut players);
+}
+
+fn save_profile(players: &mut Players) {
+    if let Some(profile) = players.profile_mut() {
+        profile.touch();
+        if let Err(error) = profile.save() {
+            eprintln!("Could not save the profile: {}", error);
+        }
     }
 }
 
+// The profile picked on the start screen has just sat down at the first human seat, its session starts here
+fn apply_profile_system(
+    mut players: ResMut<Players>,
+    mut counting: ResMut<CountingPractice>,
+    rules: Res<Rules>,
+) {
+    let owner = match players.owner {
+        Some(owner) => owner,
+        None => return,
+    };
+    let player = &mut players.seats[owner];
+    player.history.clear();
+    player.profile.fit_bet(&rules);
+    player.profile.touch();
+    counting.counter.system = player.profile.preferences.counting_system;
+    println!("Playing as {}, bankroll {}", player.profile.name, player.profile.money);
+}
+
 fn clean_up_system(
     mut commands: Commands,
-    mut cords: ResMut<Coordinates>,
+    mut layout: ResMut<TableLayout>,
     mut cards: Query<(Entity, With<Card>)>,
-    mut card_piles: ResMut<CardPiles>,
+    hand_values: Query<(Entity, &UiPlayerHandValue)>,
+    Table { mut card_piles, mut game_rng, rules, .. }: Table,
+    mut side_bet_results: ResMut<SideBetResults>,
+    mut counting: ResMut<CountingPractice>,
 ) {
     for entity in cards.iter_mut() {
         commands.entity(entity.0).despawn();
     }
-    cords.player_deal_pos_x = 0.0;
-    cords.player_deal_pos_z = 1.0;
-    cords.dealer_deal_pos_x = 50.0;
-    cords.dealer_deal_pos_z = 1.0;
-    card_piles.deck = init_deck();
-    card_piles.player_hand = Vec::new();
-    card_piles.dealer_hand = Vec::new();
+    // Only the first hand value text of each seat is kept around, the rest were spawned for split hands
+    for (entity, hand_value) in hand_values.iter() {
+        if hand_value.hand > 0 {
+            commands.entity(entity).despawn();
+        }
+    }
+    for seat_layout in layout.seats.iter_mut() {
+        seat_layout.reset();
+    }
+    layout.dealer_deal_pos_x = 50.0;
+    layout.dealer_deal_pos_z = 1.0;
+    if let Some(round) = card_piles.round.take() {
+        if round.clear(&mut card_piles.shoe, &mut game_rng.rng, &rules) {
+            println!("Shuffling a new shoe");
+            counting.counter.reset();
+        }
+    }
+    card_piles.events.clear();
+    card_piles.hole_card_hidden = false;
+    side_bet_results.0.clear();
+}
+
+fn update_discard_tray_system(
+    card_piles: Res<CardPiles>,
+    mut query: Query<&mut Visibility, With<DiscardTray>>,
+) {
+    for mut visibility in query.iter_mut() {
+        visibility.is_visible = !card_piles.shoe.discards.is_empty();
+    }
 }
 
 fn update_value_text_system(
-    mut player_query: Query<&mut Text, (With<UiPlayerHandValue>, Without<UiDealerHandValue>)>,
+    mut player_query: Query<(&mut Text, &UiPlayerHandValue), Without<UiDealerHandValue>>,
     mut dealer_query: Query<&mut Text, (With<UiDealerHandValue>, Without<UiPlayerHandValue>)>,
     card_piles: ResMut<CardPiles>,
 ) {
-    for mut text in player_query.iter_mut() {
-        update_value_text(&mut text, &card_piles.player_hand, false);
+    // The engine is ahead of the table while cards are being dealt, the totals catch up once they're all down
+    if !card_piles.events.is_empty() {
+        return;
+    }
+    let round = match &card_piles.round {
+        Some(round) => round,
+        None => {
+            for (mut text, _) in player_query.iter_mut() {
+                update_value_text(&mut text, None);
+            }
+            for mut text in dealer_query.iter_mut() {
+                update_value_text(&mut text, None);
+            }
+            return;
+        },
+    };
+
+    for (mut text, hand_index) in player_query.iter_mut() {
+        let seat = &round.seats[hand_index.seat];
+        match seat.hands.get(hand_index.hand) {
+            Some(hand) => {
+                let eval = hand.eval();
+                update_value_text(&mut text, Some(eval));
+                if hand.surrendered {
+                    text.sections[0].value = "Surrender".to_string();
+                    text.sections[0].style.color = Color::GRAY;
+                    continue;
+                }
+                // Highlight the hand being played when there are several of them
+                if seat.hands.len() > 1 && hand_index.hand == seat.active_hand && !eval.is_bust {
+                    text.sections[0].style.color = Color::YELLOW;
+                }
+            },
+            None => update_value_text(&mut text, None),
+        }
     }
 
     for mut text in dealer_query.iter_mut() {
-        update_value_text(&mut text, &card_piles.dealer_hand, true);
+        if card_piles.hole_card_hidden {
+            update_value_text(&mut text, Some(HandEval::of(&[round.dealer_hand[0]]))); // only the upcard is known
+        } else {
+            update_value_text(&mut text, Some(HandEval::of(&round.dealer_hand)));
+        }
     }
 }
 
-fn update_value_text(text: &mut Text, hand: &Vec<PlayingCard>, is_dealer: bool) {
-    if hand.len() == 0 {
-        text.sections[0].value = format!("");
-        text.sections[0].style.color = Color::WHITE;
-    } else if hand_value(&hand) == 21 && ((hand.len() == 2 && !is_dealer) || (hand.len() == 3 && is_dealer)) {
-        text.sections[0].value = format!("BJ!");
-    } else {
-        text.sections[0].value = format!("{}", hand_value(&hand));
+fn update_value_text(text: &mut Text, eval: Option<HandEval>) {
+    match eval {
+        Some(eval) if eval.cards > 0 => {
+            if eval.is_blackjack {
+                text.sections[0].value = "BJ!".to_string();
+            } else {
+                text.sections[0].value = format!("{}", eval);
+            }
+            if eval.is_bust {
+                text.sections[0].style.color = Color::RED;
+            } else {
+                text.sections[0].style.color = Color::WHITE;
+            }
+        },
+        _ => {
+            text.sections[0].value = String::new();
+            text.sections[0].style.color = Color::WHITE;
+        },
     }
-    if hand_value(&hand) > 21 {
-        text.sections[0].style.color = Color::RED;
+}
+
+// The seat the round is waiting on is highlighted
+fn update_seat_text_system(
+    players: Res<Players>,
+    card_piles: Res<CardPiles>,
+    mut query: Query<(&mut Text, &UiSeat)>,
+) {
+    let active_seat = match &card_piles.round {
+        Some(round) if round.phase != Phase::Finished && card_piles.events.is_empty() => Some(round.active_seat),
+        _ => None,
+    };
+    for (mut text, seat) in query.iter_mut() {
+        let player = &players.seats[seat.0];
+        text.sections[0].value = format!("{} {}", player.profile.name, player.profile.money);
+        text.sections[0].style.color = if active_seat == Some(seat.0) { Color::YELLOW } else { Color::WHITE };
     }
 }
 
+fn update_side_bet_text_system(
+    side_bet_results: Res<SideBetResults>,
+    mut query: Query<&mut Text, With<UiSideBetResults>>,
+) {
+    for mut text in query.iter_mut() {
+        text.sections[0].value = side_bet_results.0.join("\n");
+    }
+}
 
-fn update_control_guide_system(
-    blackjack_state: ResMut<State<BlackjackState>>,
-    mut query: Query<&mut Text, With<UiBlackjackControlsGuide>>,
+fn update_trainer_text_system(
+    training: Res<TrainingSession>,
+    mut query: Query<&mut Text, With<UiTrainer>>,
 ) {
     for mut text in query.iter_mut() {
-        match blackjack_state.current() {
-            BlackjackState::PreGame => text.sections[0].value = format!(" \nZ: Start\nC: Change Bet"),
-            BlackjackState::ChangeBet => text.sections[0].value = format!("Z: Raise Bet\nX: Lower Bet\nC: Confirm Bet"),
-            BlackjackState::InitialDraw => text.sections[0].value = format!(""),
-            BlackjackState::PlayerTurn => text.sections[0].value = format!(" \nZ: Hit\nX: Stand"),
-            BlackjackState::DealerTurn => text.sections[0].value = format!(""),
-            BlackjackState::PlayerDraw => text.sections[0].value = format!(""),
-            BlackjackState::DealerDraw => text.sections[0].value = format!(""),
-            BlackjackState::GameEnd => text.sections[0].value = format!(" \n \nZ: Cleanup"),
-            BlackjackState::CleanUp => text.sections[0].value = format!(" \n \nZ: Next"),
+        if !training.active {
+            text.sections[0].value = String::new();
+            continue;
+        }
+        let (right, total) = training.trainer.score();
+        let mut value = format!("Trainer: {} of {} correct", right, total);
+        match &training.last_decision {
+            Some(decision) => {
+                value.push_str(&format!("\n{}", decision.feedback()));
+                text.sections[0].style.color = if decision.is_correct() { Color::GREEN } else { Color::RED };
+            },
+            None => text.sections[0].style.color = Color::WHITE,
         }
+        text.sections[0].value = value;
     }
-} 
-
-
-
-fn init_deck() -> Vec<PlayingCard> {
-    let mut deck = Vec::new();
-    let mut current_suit = CardSuit::Heart;
-    for i in 1..53 {
+}
 
-        if i % 13 == 0 {
-            deck.push(PlayingCard { suit: current_suit, value: 13});    
-            match current_suit {
-                CardSuit::Heart => current_suit = CardSuit::Diamond,
-                CardSuit::Diamond => current_suit = CardSuit::Spade,
-                CardSuit::Spade => current_suit = CardSuit::Club,
-                CardSuit::Club => current_suit = CardSuit::Heart,
-            }
-        }
-        else {
-            deck.push(PlayingCard { suit: current_suit, value: i % 13});        
+fn update_count_text_system(
+    blackjack_state: Res<State<BlackjackState>>,
+    counting: Res<CountingPractice>,
+    mut query: Query<&mut Text, With<UiCount>>,
+) {
+    for mut text in query.iter_mut() {
+        let mut value = String::new();
+        if *blackjack_state.current() == BlackjackState::CountQuiz {
+            value = format!("Running count ({}): {}", counting.counter.system, counting.guess);
+        } else if counting.reveal && cfg!(debug_assertions) {
+            value = format!("{} running {} true {:.1}", counting.counter.system, counting.counter.running_count(), counting.counter.true_count());
         }
+        text.sections[0].value = value;
     }
+}
 
-    deck.shuffle(&mut rand::thread_rng());
+fn update_table_rules_text_system(
+    rules: Res<Rules>,
+    game_rng: Res<GameRng>,
+    mut query: Query<&mut Text, With<UiTableRules>>,
+) {
+    for mut text in query.iter_mut() {
+        text.sections[0].value = table_rules_text(&rules, game_rng.seed);
+    }
+}
 
-    return deck;
+fn table_rules_text(rules: &TableRules, seed: u64) -> String {
+    let mut text = format!("Blackjack pays {}\n", rules.blackjack_payout);
+    if rules.dealer_hits_soft_17 {
+        text.push_str("Dealer hits soft 17");
+    } else {
+        text.push_str("Dealer stands on all 17s");
+    }
+    if rules.dealer_22_pushes {
+        text.push_str("\nDealer 22 pushes");
+    }
+    text.push_str(&format!("\nSeed {}", seed)); // for replaying the session
+    text
 }
 
-fn get_card(deck: &mut Vec<PlayingCard>) -> PlayingCard {
-    let i = (rand::random::<f32>() * deck.len() as f32).floor() as usize;
-    let card = deck.remove(i);
-    return card;
+fn update_control_guide_system(
+    blackjack_state: ResMut<State<BlackjackState>>,
+    selected_wager: Res<SelectedWager>,
+    Table { card_piles, players, rules, .. }: Table,
+    Practice { advisor, counting, .. }: Practice,
+    autoplay: Res<Autoplay>,
+    mut query: Query<&mut Text, With<UiBlackjackControlsGuide>>,
+) {
+    let player = players.current();
+    for mut text in query.iter_mut() {
+        let deciding = matches!(blackjack_state.current(), BlackjackState::EarlySurrender | BlackjackState::Insurance | BlackjackState::EvenMoney | BlackjackState::PlayerTurn);
+        if deciding && player.strategy.is_some() {
+            text.sections[0].value = format!(" \n \n{} is thinking", player.profile.name);
+            continue;
+        }
+        if autoplay.active && *blackjack_state.current() != BlackjackState::Dealing {
+            text.sections[0].value = " \nAutoplay\nA: Take Over".to_string();
+            continue;
+        }
+        match blackjack_state.current() {
+            BlackjackState::StartScreen => text.sections[0].value = String::new(),
+            BlackjackState::PreGame => text.sections[0].value = "Z: Start\nC: Change Bet\nH: Advice\nT: Trainer\nK: Counting".to_string(),
+            BlackjackState::GameOver => {
+                let mut guide = "Game Over".to_string();
+                if rules.game_over.bailout.is_positive() {
+                    guide.push_str(&format!("\nZ: Bailout {}", rules.game_over.bailout));
+                }
+                if loan_offered(player, &rules) {
+                    guide.push_str(&format!("\nX: Loan {} ({}% interest)", rules.game_over.loan, rules.game_over.interest_percent));
+                }
+                guide.push_str("\nEsc: Profiles");
+                text.sections[0].value = guide;
+            },
+            BlackjackState::ChangeBet => {
+                // Past the last side bet V hands the keyboard to the next human seat
+                let next = if selected_wager.0.next(&rules) == Wager::Bet && players.next_human() != players.current { "Next Player" } else { "Next Wager" };
+                text.sections[0].value = format!("Z: Raise {0}\nX: Lower {0}\nV: {1}\nC: Confirm Bet", selected_wager.0.name(&rules), next);
+            },
+            BlackjackState::Dealing => text.sections[0].value = String::new(),
+            BlackjackState::EarlySurrender => text.sections[0].value = " \nZ: Surrender\nX: Continue".to_string(),
+            BlackjackState::Insurance => text.sections[0].value = "Z: Raise Insurance\nX: Lower Insurance\nC: Confirm".to_string(),
+            BlackjackState::EvenMoney => text.sections[0].value = " \nZ: Even Money\nX: Decline".to_string(),
+            BlackjackState::PlayerTurn => {
+                let round = match &card_piles.round {
+                    Some(round) if round.phase == Phase::PlayerTurn => round,
+                    _ => {
+                        text.sections[0].value = String::new();
+                        continue;
+                    },
+                };
+                let mut guide = if round.can_hit(&rules) { "Z: Hit\nX: Stand".to_string() } else { "X: Stand".to_string() };
+                if round.can_double(player.profile.money, &rules) {
+                    guide.push_str("\nV: Double");
+                }
+                if round.can_split(player.profile.money, &rules) {
+                    guide.push_str("\nB: Split");
+                }
+                if round.can_surrender(&rules) {
+                    guide.push_str("\nN: Surrender");
+                }
+                if players.profile().is_some_and(|profile| profile.preferences.advice) {
+                    if let Some(action) = advisor.strategy.advise(&PlayerView::new(round, player.profile.money, counting.counter.seen(), &rules)) {
+                        guide.insert_str(0, &format!("Advice: {}\n", basic_strategy::action_name(action)));
+                    }
+                }
+                if guide.lines().count() < 3 {
+                    guide.insert_str(0, " \n");
+                }
+                text.sections[0].value = guide;
+            },
+            BlackjackState::GameEnd => text.sections[0].value = " \n \nZ: Cleanup".to_string(),
+            BlackjackState::CountQuiz => text.sections[0].value = "Z: Raise Count\nX: Lower Count\nC: Answer".to_string(),
+            BlackjackState::CleanUp => text.sections[0].value = " \n \nZ: Next".to_string(),
+        }
+    }
 }
 
 // translate PlayingCard struct to the corresponding index in card_sheet.png
 pub fn card_to_asset_index(card: &PlayingCard) -> usize {
     match card.suit {
-        CardSuit::Heart => return (card.value - 1) as usize,
-        CardSuit::Diamond => return (card.value + 12 ) as usize,
-        CardSuit::Spade => return (card.value + 25) as usize,
-        CardSuit::Club => return (card.value + 38) as usize,
+        CardSuit::Heart => (card.value - 1) as usize,
+        CardSuit::Diamond => (card.value + 12 ) as usize,
+        CardSuit::Spade => (card.value + 25) as usize,
+        CardSuit::Club => (card.value + 38) as usize,
     }
 }
 
-fn hand_value(hand: &Vec<PlayingCard>) -> u8 {
-    let mut hand_value: u8 = 0;
-    let mut aces: u8 = 0;
-    for i in 0..hand.len() {
-        hand_value += cmp::min(hand[i].value, 10);
-        if hand[i].value == 1 {
-             aces += 1;
+// Everything the player puts on the table when a round starts
+fn round_cost(player: &Player) -> Money {
+    player.profile.bet + player.side_bets.iter().sum::<Money>()
+}
+
+// Every seat that can cover its bet joins the round, computer players and autoplay pick their bet first. Returns whether the round started.
+fn start_round(players: &mut Players, card_piles: &mut CardPiles, seen: &[PlayingCard], autoplay: Option<&dyn Strategy>, rng: &mut ShoeRng, rules: &TableRules, blackjack_state: &mut State<BlackjackState>) -> bool {
+    let seats = players.seats.len();
+    let mut bets = Vec::new();
+    for player in players.seats.iter_mut() {
+        if let Some(strategy) = player.strategy.as_deref().or(autoplay) {
+            let view = BankrollView { money: player.profile.money, history: &player.history, seen, rules };
+            player.profile.bet = strategy.bet(&view);
         }
-    }
-    while aces > 0 && hand_value != 21 {
-        if hand_value + 10 <= 21 {
-            hand_value += 10;
+        if player.profile.bet.is_positive() && round_cost(player) <= player.profile.money {
+            bets.push(SeatBet { bet: player.profile.bet, side_bets: player.side_bets.clone() });
+        } else {
+            if seats > 1 {
+                println!("{} sits this round out", player.profile.name);
+            }
+            bets.push(SeatBet { bet: Money::ZERO, side_bets: Vec::new() });
         }
-        aces -= 1;
     }
-    return hand_value;
+    if bets.iter().all(|seat| !seat.bet.is_positive()) {
+        println!("{}", ActionError::NotEnoughMoney);
+        return false;
+    }
+    let mut bankrolls = players.bankrolls();
+    match Round::start(bets, &mut bankrolls, &mut card_piles.shoe, rng, rules) {
+        Ok((round, events)) => {
+            players.set_bankrolls(bankrolls);
+            card_piles.round = Some(round);
+            card_piles.events.extend(events);
+            blackjack_state.set(BlackjackState::Dealing).unwrap();
+            true
+        },
+        Err(error) => {
+            println!("{}", error);
+            false
+        },
+    }
+}
+
+// Hands the active seat's decision to the engine and draws what it led to, returns whether the decision was allowed
+fn play(action: Action, players: &mut Players, card_piles: &mut CardPiles, rng: &mut ShoeRng, rules: &TableRules, blackjack_state: &mut State<BlackjackState>) -> bool {
+    let round = match card_piles.round.as_mut() {
+        Some(round) => round,
+        None => return false,
+    };
+    let phase = round.phase;
+    let name = players.seats[round.active_seat].profile.name.clone();
+    let mut bankrolls = players.bankrolls();
+    match round.act(action, &mut bankrolls, &mut card_piles.shoe, rng, rules) {
+        Ok(events) => {
+            players.set_bankrolls(bankrolls);
+            match (phase, action) {
+                (_, Action::Hit) => println!("{} hits", name),
+                (_, Action::Stand) => println!("{} stands", name),
+                (_, Action::Double) => println!("{} doubles down", name),
+                (_, Action::Split) => println!("{} splits", name),
+                (_, Action::Surrender) => println!("{} surrenders", name),
+                (_, Action::Insurance(_)) => println!("{} takes insurance", name),
+                (_, Action::EvenMoney) => println!("{} takes even money", name),
+                (Phase::EvenMoney, Action::Decline) => println!("{} declines even money", name),
+                (_, Action::Decline) => {},
+            }
+            card_piles.events.extend(events);
+            blackjack_state.set(BlackjackState::Dealing).unwrap();
+            true
+        },
+        Err(error) => {
+            println!("{}", error);
+            false
+        },
+    }
 }
 
 fn blackjack_control_system(
     mut keyboard: ResMut<Input<KeyCode>>,
     mut blackjack_state: ResMut<State<BlackjackState>>,
-    mut player: ResMut<Player>,
+    Table { mut players, mut card_piles, mut game_rng, rules, .. }: Table,
+    Practice { advisor, mut training, mut counting, .. }: Practice,
+    mut selected_wager: ResMut<SelectedWager>,
+    mut autoplay: ResMut<Autoplay>,
 ) {
-    if keyboard.just_pressed(KeyCode::Z) {
+    // The start screen and the game over screen have their own controls
+    if matches!(blackjack_state.current(), BlackjackState::StartScreen | BlackjackState::GameOver) {
+        return;
+    }
+    // Computer players decide on their own, the keyboard only plays for humans and leaves the table alone during autoplay
+    let human_turn = players.current().strategy.is_none();
+    let keys_play = !autoplay.active;
+    let mut action = None;
+    if keyboard.just_pressed(KeyCode::Z) && keys_play {
         keyboard.clear_just_pressed(KeyCode::Z);
         match blackjack_state.current() {
+            BlackjackState::StartScreen => {},
+            BlackjackState::GameOver => {},
             BlackjackState::PreGame => {
-                if player.money - player.bet < 0.0 {
-                    println!("Not enough money for this bet!")
-                } else {
-                    player.money = player.money - player.bet;
-                    blackjack_state.set(BlackjackState::InitialDraw,).unwrap()
+                start_round(&mut players, &mut card_piles, counting.counter.seen(), None, &mut game_rng.rng, &rules, &mut blackjack_state);
+            },
+            BlackjackState::ChangeBet => {
+                let player = players.current_mut();
+                if round_cost(player) + rules.bet_step <= player.profile.money {
+                    match selected_wager.0 {
+                        Wager::Bet => if player.profile.bet + rules.bet_step <= rules.max_bet {player.profile.bet += rules.bet_step;},
+                        Wager::SideBet(i) => if player.side_bets[i] + rules.bet_step <= rules.max_side_bet {player.side_bets[i] += rules.bet_step;},
+                    }
                 }
             },
-            BlackjackState::ChangeBet => if player.bet + BET_STEP <= MAX_BET && player.bet + BET_STEP <= player.money {player.bet += BET_STEP;},
-            BlackjackState::InitialDraw => {},
-            BlackjackState::PlayerTurn => {
-                println!("Player hit");
-                blackjack_state.set(BlackjackState::PlayerDraw).unwrap();
+            BlackjackState::Dealing => {},
+            BlackjackState::EarlySurrender => action = Some(Action::Surrender),
+            BlackjackState::Insurance => if human_turn {
+                // Insurance is a side wager of up to half the bet
+                let player = players.current_mut();
+                let insurance = player.insurance + rules.insurance_step();
+                let bet = card_piles.round.as_ref().map_or(Money::ZERO, |round| round.current_hand().bet);
+                if insurance <= rules.max_insurance(bet) && insurance <= player.profile.money {
+                    player.insurance = insurance;
+                }
+            },
+            BlackjackState::EvenMoney => action = Some(Action::EvenMoney),
+            BlackjackState::PlayerTurn => action = Some(Action::Hit),
+            BlackjackState::GameEnd => {
+                counting.rounds += 1;
+                if counting.active && counting.rounds >= QUIZ_EVERY {
+                    counting.rounds = 0;
+                    counting.guess = counting.counter.system.initial_count(rules.decks);
+                    println!("What's the running count?");
+                    blackjack_state.set(BlackjackState::CountQuiz).unwrap();
+                } else {
+                    blackjack_state.set(BlackjackState::CleanUp,).unwrap();
+                }
             },
-            BlackjackState::DealerTurn => {},
-            BlackjackState::PlayerDraw => {},
-            BlackjackState::DealerDraw => {},
-            BlackjackState::GameEnd => blackjack_state.set(BlackjackState::CleanUp,).unwrap(),
+            BlackjackState::CountQuiz => counting.guess += 1,
             BlackjackState::CleanUp => blackjack_state.set(BlackjackState::PreGame,).unwrap(),
         }
-    } else if keyboard.just_pressed(KeyCode::X) {
+    } else if keyboard.just_pressed(KeyCode::X) && keys_play {
         keyboard.clear_just_pressed(KeyCode::X);
         match blackjack_state.current() {
+            BlackjackState::StartScreen => {},
+            BlackjackState::GameOver => {},
             BlackjackState::PreGame => {},
-            BlackjackState::ChangeBet => if player.bet - BET_STEP >= MIN_BET {player.bet -= BET_STEP;},
-            BlackjackState::InitialDraw => {},
-            BlackjackState::PlayerTurn => {
-                println!("Player stands");
-                blackjack_state.set(BlackjackState::DealerTurn).unwrap();},
-            BlackjackState::DealerTurn => {},
-            BlackjackState::PlayerDraw => {},
-            BlackjackState::DealerDraw => {},
+            BlackjackState::ChangeBet => {
+                let player = players.current_mut();
+                match selected_wager.0 {
+                    Wager::Bet => if player.profile.bet - rules.bet_step >= rules.min_bet {player.profile.bet -= rules.bet_step;},
+                    Wager::SideBet(i) => if player.side_bets[i] - rules.bet_step >= Money::ZERO {player.side_bets[i] -= rules.bet_step;},
+                }
+            },
+            BlackjackState::Dealing => {},
+            BlackjackState::EarlySurrender => action = Some(Action::Decline),
+            BlackjackState::Insurance => if human_turn {
+                let player = players.current_mut();
+                if player.insurance - rules.insurance_step() >= Money::ZERO {player.insurance -= rules.insurance_step();}
+            },
+            BlackjackState::EvenMoney => action = Some(Action::Decline),
+            BlackjackState::PlayerTurn => action = Some(Action::Stand),
             BlackjackState::GameEnd => {},
+            BlackjackState::CountQuiz => counting.guess -= 1,
             BlackjackState::CleanUp => {},
         }
-    } else if keyboard.just_pressed(KeyCode::C) {
+    } else if keyboard.just_pressed(KeyCode::C) && keys_play {
         keyboard.clear_just_pressed(KeyCode::C);
         match blackjack_state.current() {
+            BlackjackState::StartScreen => {},
+            BlackjackState::GameOver => {},
             BlackjackState::PreGame => blackjack_state.set(BlackjackState::ChangeBet,).unwrap(),
             BlackjackState::ChangeBet => blackjack_state.set(BlackjackState::PreGame).unwrap(),
-            BlackjackState::InitialDraw => {},
+            BlackjackState::Dealing => {},
+            BlackjackState::EarlySurrender => {},
+            BlackjackState::Insurance => {
+                let player = players.current();
+                if player.insurance.is_positive() {
+                    action = Some(Action::Insurance(player.insurance));
+                } else {
+                    action = Some(Action::Decline);
+                }
+            },
+            BlackjackState::EvenMoney => {},
             BlackjackState::PlayerTurn => {},
-            BlackjackState::DealerTurn => {},
-            BlackjackState::PlayerDraw => {},
-            BlackjackState::DealerDraw => {},
             BlackjackState::GameEnd => {},
+            BlackjackState::CountQuiz => {
+                let count = counting.counter.running_count();
+                counting.asked += 1;
+                if counting.guess == count {
+                    counting.right += 1;
+                    println!("Correct! The running count is {}", count);
+                } else {
+                    println!("The running count is {}, not {}", count, counting.guess);
+                }
+                if counting.counter.system.is_balanced() {
+                    println!("True count {:.1} with {:.1} decks left", counting.counter.true_count(), counting.counter.decks_remaining());
+                }
+                println!("Counting quizzes: {} of {} right", counting.right, counting.asked);
+                blackjack_state.set(BlackjackState::CleanUp).unwrap();
+            },
             BlackjackState::CleanUp => {},
         }
-    } 
-}
\ No newline at end of file
+    } else if keyboard.just_pressed(KeyCode::V) && keys_play {
+        keyboard.clear_just_pressed(KeyCode::V);
+        match blackjack_state.current() {
+            BlackjackState::ChangeBet => {
+                // After the last side bet the next human at the table gets to place their bets
+                selected_wager.0 = selected_wager.0.next(&rules);
+                if selected_wager.0 == Wager::Bet {
+                    players.current = players.next_human();
+                }
+            },
+            BlackjackState::PlayerTurn => action = Some(Action::Double),
+            _ => {},
+        }
+    } else if keyboard.just_pressed(KeyCode::B) && keys_play {
+        keyboard.clear_just_pressed(KeyCode::B);
+        if *blackjack_state.current() == BlackjackState::PlayerTurn {
+            action = Some(Action::Split);
+        }
+    } else if keyboard.just_pressed(KeyCode::N) && keys_play {
+        keyboard.clear_just_pressed(KeyCode::N);
+        if *blackjack_state.current() == BlackjackState::PlayerTurn {
+            action = Some(Action::Surrender);
+        }
+    } else if keyboard.just_pressed(KeyCode::T) {
+        keyboard.clear_just_pressed(KeyCode::T);
+        if training.active {
+            training.end();
+        } else {
+            training.active = true;
+            println!("Trainer mode on, every decision is graded against basic strategy. Press T again for the report");
+        }
+    } else if keyboard.just_pressed(KeyCode::K) {
+        keyboard.clear_just_pressed(KeyCode::K);
+        counting.active = !counting.active;
+        if counting.active {
+            counting.rounds = 0;
+            println!("Counting practice on with {}, J switches the system. The running count so far is {}", counting.counter.system, counting.counter.running_count());
+        } else {
+            println!("Counting practice off, {} of {} quizzes right", counting.right, counting.asked);
+            counting.asked = 0;
+            counting.right = 0;
+        }
+    } else if keyboard.just_pressed(KeyCode::J) {
+        keyboard.clear_just_pressed(KeyCode::J);
+        if counting.active {
+            counting.counter.system = counting.counter.system.next();
+            if let Some(profile) = players.profile_mut() {
+                profile.preferences.counting_system = counting.counter.system;
+            }
+            println!("Counting with {}, the running count so far is {}", counting.counter.system, counting.counter.running_count());
+        }
+    } else if keyboard.just_pressed(KeyCode::R) && cfg!(debug_assertions) {
+        keyboard.clear_just_pressed(KeyCode::R);
+        counting.reveal = !counting.reveal;
+    } else if keyboard.just_pressed(KeyCode::A) {
+        keyboard.clear_just_pressed(KeyCode::A);
+        autoplay.active = !autoplay.active;
+        if autoplay.active {
+            println!("Autoplay on, basic strategy plays for you. Press A to take over");
+        } else {
+            println!("Autoplay off");
+        }
+    } else if keyboard.just_pressed(KeyCode::H) {
+        keyboard.clear_just_pressed(KeyCode::H);
+        if let Some(profile) = players.profile_mut() {
+            profile.preferences.advice = !profile.preferences.advice;
+            if profile.preferences.advice {
+                println!("Strategy advice on");
+            } else {
+                println!("Strategy advice off");
+            }
+        }
+    }
+
+    if let Some(action) = action.filter(|_| human_turn) {
+        // The decision is graded against the hand as it was before the action
+        let decision = match &card_piles.round {
+            Some(round) if training.active => Decision::grade(action, &PlayerView::new(round, players.current().profile.money, counting.counter.seen(), &rules), &advisor.strategy),
+            _ => None,
+        };
+        if play(action, &mut players, &mut card_piles, &mut game_rng.rng, &rules, &mut blackjack_state) {
+            if let Some(decision) = decision {
+                println!("{}", decision.feedback());
+                training.trainer.record(&decision);
+                training.last_decision = Some(decision);
+            }
+        }
+    }
+}
+
+impl TrainingSession {
+    fn end(&mut self) {
+        println!("{}", self.trainer.report());
+        *self = TrainingSession::default();
+    }
+}
+
+// Back on the start screen the session at the table is over
+fn end_training_system(
+    mut training: ResMut<TrainingSession>,
+) {
+    if training.active {
+        training.end();
+    }
+}
+
+fn close_training_system(
+    mut close_requests: EventReader<WindowCloseRequested>,
+    mut training: ResMut<TrainingSession>,
+) {
+    if close_requests.iter().count() > 0 && training.active {
+        training.end();
+    }
+}
+
+// Computer seats take their turns here, and while autoplay is on so do the human seats along with starting and clearing
+// rounds. Every decision waits AI_DELAY so the table can be followed, and goes through the same calls as the keyboard.
+fn bot_system(
+    time: Res<Time>,
+    mut pace: ResMut<AiPace>,
+    mut blackjack_state: ResMut<State<BlackjackState>>,
+    Table { mut players, mut card_piles, mut game_rng, rules, .. }: Table,
+    mut autoplay: ResMut<Autoplay>,
+    counting: Res<CountingPractice>,
+) {
+    let state = *blackjack_state.current();
+    let bot_turn = match state {
+        BlackjackState::PreGame | BlackjackState::GameEnd | BlackjackState::CleanUp => autoplay.active,
+        BlackjackState::EarlySurrender | BlackjackState::Insurance | BlackjackState::EvenMoney | BlackjackState::PlayerTurn => {
+            players.current().strategy.is_some() || autoplay.active
+        },
+        BlackjackState::StartScreen | BlackjackState::GameOver | BlackjackState::ChangeBet | BlackjackState::Dealing | BlackjackState::CountQuiz => false,
+    };
+    if !bot_turn || !pace.0.tick(time.delta()).finished() {
+        return;
+    }
+    pace.0.reset();
+
+    match state {
+        BlackjackState::PreGame => {
+            if !start_round(&mut players, &mut card_piles, counting.counter.seen(), Some(autoplay.strategy.as_ref()), &mut game_rng.rng, &rules, &mut blackjack_state) {
+                autoplay.active = false;
+                println!("Autoplay off");
+            }
+        },
+        BlackjackState::GameEnd => blackjack_state.set(BlackjackState::CleanUp).unwrap(),
+        BlackjackState::CleanUp => blackjack_state.set(BlackjackState::PreGame).unwrap(),
+        _ => {
+            let (action, phase) = match &card_piles.round {
+                Some(round) if round.phase != Phase::Finished => {
+                    let player = &players.seats[round.active_seat];
+                    let strategy = player.strategy.as_deref().unwrap_or(autoplay.strategy.as_ref());
+                    (strategy.decide(&PlayerView::new(round, player.profile.money, counting.counter.seen(), &rules)), round.phase)
+                },
+                _ => return,
+            };
+            if !play(action, &mut players, &mut card_piles, &mut game_rng.rng, &rules, &mut blackjack_state) {
+                // A strategy that picks something the round won't take stands or declines rather than holding up the table
+                let fallback = if phase == Phase::PlayerTurn { Action::Stand } else { Action::Decline };
+                play(fallback, &mut players, &mut card_piles, &mut game_rng.rng, &rules, &mut blackjack_state);
+            }
+        },
+    }
+}
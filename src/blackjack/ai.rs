@@ -0,0 +1,89 @@
+use std::fmt;
+use serde::Deserialize;
+use super::counting::CountingSystem;
+use super::engine::{Phase, Action, CardColour};
+use super::money::Money;
+use super::rules::TableRules;
+use super::strategy::{Strategy, PlayerView, BankrollView, Basic, NeverBust, MimicDealer, Counting};
+
+// Computer players for the other seats at the table. Each personality is one of the built-in strategies, or the hunch
+// player below, and sees the table through the same views as every other strategy.
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Personality {
+    BasicStrategy, // plays the chart perfectly and flat bets the minimum
+    Counter, // plays the chart, counts Hi-Lo and spreads the bet with the true count
+    NeverBust, // stands on anything that could bust
+    MimicDealer, // hits below 17 like the dealer
+    Hunch, // trusts lucky cards and chases losses
+}
+
+// Splits every pair, doubles whenever a seven shows up, never stops on an unlucky 13 and plays a red dealer card as hot
+pub struct Hunch;
+
+impl Personality {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Personality::BasicStrategy => "Basic",
+            Personality::Counter => "Counter",
+            Personality::NeverBust => "Never Bust",
+            Personality::MimicDealer => "Mimic",
+            Personality::Hunch => "Hunch",
+        }
+    }
+
+    pub fn strategy(&self, rules: &TableRules) -> Box<dyn Strategy> {
+        match self {
+            Personality::BasicStrategy => Box::new(Basic::new(rules)),
+            Personality::Counter => Box::new(Counting::new(CountingSystem::HiLo, rules)),
+            Personality::NeverBust => Box::new(NeverBust),
+            Personality::MimicDealer => Box::new(MimicDealer),
+            Personality::Hunch => Box::new(Hunch),
+        }
+    }
+}
+
+impl fmt::Display for Personality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Strategy for Hunch {
+    fn decide(&self, view: &PlayerView) -> Action {
+        match view.phase {
+            // Never turns down a sure thing
+            Phase::EvenMoney => return Action::EvenMoney,
+            Phase::EarlySurrender | Phase::Insurance => return Action::Decline,
+            Phase::PlayerTurn | Phase::Finished => {},
+        }
+        let eval = view.hand.eval();
+        if view.can_split {
+            return Action::Split;
+        }
+        if !view.can_hit {
+            return Action::Stand;
+        }
+        if view.hand.cards.iter().any(|card| card.value == 7) && view.can_double {
+            return Action::Double;
+        }
+        if eval.best_total == 13 {
+            return Action::Hit;
+        }
+        let stand_on = match view.dealer_upcard.suit.colour() {
+            CardColour::Red => 17,
+            CardColour::Black => 12,
+        };
+        if eval.best_total < stand_on {
+            Action::Hit
+        } else {
+            Action::Stand
+        }
+    }
+
+    // Every loss in a row makes a win more overdue
+    fn bet(&self, view: &BankrollView) -> Money {
+        let losing_streak = view.history.iter().rev().take_while(|net| net.is_negative()).count();
+        view.table_bet(view.rules.min_bet + view.rules.bet_step * losing_streak as i64)
+    }
+}
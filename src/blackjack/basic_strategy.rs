@@ -0,0 +1,449 @@
+use super::engine::{Phase, Action, PlayingCard};
+use super::rules::{TableRules, DoubleRule, HoleCardRule, SurrenderRule};
+use super::strategy::PlayerView;
+
+// Basic strategy worked out from the table rules instead of copied from a printed chart. Every cell of the chart holds the
+// expected value of each action for a starting hand against a dealer upcard, with those three cards taken out of the shoe.
+// Later cards are drawn as if the shoe didn't change, which is how the usual charts are computed too.
+
+// Index 0 is the dealer's 2 and index 9 the ace
+const UPCARDS: usize = 10;
+
+pub struct BasicStrategy {
+    hard: Vec<[ActionValues; UPCARDS]>, // hard 4 to 21
+    soft: Vec<[ActionValues; UPCARDS]>, // soft 12 (two aces) to 21
+    pairs: Vec<[ActionValues; UPCARDS]>, // a pair of aces to a pair of tens
+}
+
+// What each action is worth in initial bets, None when the hand can't take it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActionValues {
+    pub stand: f64,
+    pub hit: f64,
+    pub double: f64,
+    pub split: Option<f64>,
+    pub surrender: Option<f64>,
+}
+
+// Chances of the next card's value, index 1 is an ace and index 10 any ten valued card
+type Odds = [f64; 11];
+
+// How the dealer's hand ends up once it's played out, given that it isn't a blackjack
+#[derive(Debug, Clone, Copy, Default)]
+struct DealerOutcomes {
+    totals: [f64; 5], // 17 to 21
+    bust_22: f64,
+    bust: f64, // over 22
+}
+
+impl ActionValues {
+    // What the action is worth, None when the hand can't take it or it isn't a playing decision
+    pub fn of(&self, action: Action) -> Option<f64> {
+        match action {
+            Action::Stand => Some(self.stand),
+            Action::Hit => Some(self.hit),
+            Action::Double => Some(self.double),
+            Action::Split => self.split,
+            Action::Surrender => self.surrender,
+            _ => None,
+        }
+    }
+}
+
+impl BasicStrategy {
+    pub fn new(rules: &TableRules) -> BasicStrategy {
+        let mut strategy = BasicStrategy { hard: Vec::new(), soft: Vec::new(), pairs: Vec::new() };
+        for total in 4..=21 {
+            let (first, second) = hard_starting_cards(total);
+            strategy.hard.push(chart_row(first, second, rules));
+        }
+        for total in 12..=21 {
+            strategy.soft.push(chart_row(1, total - 11, rules));
+        }
+        for value in 1..=10 {
+            strategy.pairs.push(chart_row(value, value, rules));
+        }
+        strategy
+    }
+
+    // The chart cell for the seat's hand against the dealer's upcard
+    pub fn values(&self, view: &PlayerView) -> ActionValues {
+        let hand = view.hand;
+        let eval = hand.eval();
+        let upcard = upcard_index(&view.dealer_upcard);
+        if view.can_split {
+            return self.pairs[card_value(&hand.cards[0]) as usize - 1][upcard];
+        }
+        if eval.is_soft {
+            return self.soft[(eval.best_total - 12) as usize][upcard];
+        }
+        self.hard[(eval.hard_total.clamp(4, 21) - 4) as usize][upcard]
+    }
+
+    // The best action the round allows right now, None when the round isn't waiting on a playing decision
+    pub fn advise(&self, view: &PlayerView) -> Option<Action> {
+        if view.phase != Phase::PlayerTurn {
+            return None;
+        }
+        let values = self.values(view);
+        let mut best = (Action::Stand, values.stand);
+        let mut consider = |action: Action, value: Option<f64>| {
+            if let Some(value) = value {
+                if value > best.1 {
+                    best = (action, value);
+                }
+            }
+        };
+        if view.can_hit {
+            consider(Action::Hit, Some(values.hit));
+        }
+        if view.can_double {
+            consider(Action::Double, Some(values.double));
+        }
+        if view.can_split {
+            consider(Action::Split, values.split);
+        }
+        if view.can_surrender {
+            consider(Action::Surrender, values.surrender);
+        }
+        Some(best.0)
+    }
+
+    // Early surrender is offered before the dealer checks for blackjack. The chart's values assume there's no blackjack
+    // when the dealer peeks, so playing on also risks losing the bet to one.
+    pub fn surrenders_early(&self, view: &PlayerView) -> bool {
+        if view.phase != Phase::EarlySurrender || view.hand.eval().is_blackjack {
+            return false;
+        }
+        let values = self.values(view);
+        let surrender = match values.surrender {
+            Some(surrender) => surrender,
+            None => return false,
+        };
+        let play_on = PlayerView { phase: Phase::PlayerTurn, can_surrender: false, ..*view };
+        let best = self.advise(&play_on).and_then(|action| values.of(action)).unwrap_or(values.stand);
+        let blackjack_chance = if view.rules.hole_card == HoleCardRule::Peek {
+            let cards = [card_value(&view.hand.cards[0]), card_value(&view.hand.cards[1]), card_value(&view.dealer_upcard)];
+            let odds = shoe_odds(view.rules.decks, &cards);
+            match cards[2] {
+                1 => odds[10],
+                10 => odds[1],
+                _ => 0.0,
+            }
+        } else {
+            0.0
+        };
+        surrender > -blackjack_chance + (1.0 - blackjack_chance) * best
+    }
+}
+
+// Two different cards other than aces for every hard starting total, 4 only comes from a pair of twos
+fn hard_starting_cards(total: u8) -> (u8, u8) {
+    match total {
+        4 => (2, 2),
+        5..=11 => (2, total - 2),
+        12..=19 => (total - 10, 10),
+        _ => (10, 10), // 20, and 21 which needs a third card anyway
+    }
+}
+
+fn chart_row(first: u8, second: u8, rules: &TableRules) -> [ActionValues; UPCARDS] {
+    let mut row = [ActionValues { stand: 0.0, hit: 0.0, double: 0.0, split: None, surrender: None }; UPCARDS];
+    for (i, upcard) in (2..=11).enumerate() {
+        let upcard = if upcard == 11 { 1 } else { upcard };
+        let odds = shoe_odds(rules.decks, &[first, second, upcard]);
+        row[i] = action_values(first, second, upcard, &odds, rules);
+    }
+    row
+}
+
+fn action_values(first: u8, second: u8, upcard: u8, odds: &Odds, rules: &TableRules) -> ActionValues {
+    let dealer = dealer_outcomes(upcard, odds, rules);
+    // Without a hole card a dealer blackjack still takes every bet on the table, doubles and splits included
+    let blackjack_chance = if rules.hole_card == HoleCardRule::NoHoleCard {
+        match upcard {
+            1 => odds[10],
+            10 => odds[1],
+            _ => 0.0,
+        }
+    } else {
+        0.0
+    };
+    let risk = |value: f64, bet: f64| blackjack_chance * -bet + (1.0 - blackjack_chance) * value;
+
+    let mut player = PlayerValues::new(odds, &dealer, rules);
+    let hard = first + second;
+    let ace = first == 1 || second == 1;
+    let split = if first == second {
+        Some(risk(2.0 * player.split_hand(first), 2.0))
+    } else {
+        None
+    };
+    let surrender = match (rules.surrender, rules.hole_card) {
+        (SurrenderRule::NoSurrender, _) => None,
+        (SurrenderRule::Late, HoleCardRule::NoHoleCard) => Some(risk(-0.5, 1.0)),
+        _ => Some(-0.5),
+    };
+    ActionValues {
+        stand: risk(player.stand(best_total(hard, ace)), 1.0),
+        hit: risk(player.hit(hard, ace), 1.0),
+        double: risk(player.double(hard, ace), 2.0),
+        split,
+        surrender,
+    }
+}
+
+// The shoe's card odds with the given cards already dealt
+fn shoe_odds(decks: usize, dealt: &[u8]) -> Odds {
+    let mut counts = [0.0; 11];
+    for (value, count) in counts.iter_mut().enumerate().skip(1) {
+        *count = if value == 10 { 16.0 } else { 4.0 } * decks as f64;
+    }
+    for value in dealt.iter() {
+        counts[*value as usize] -= 1.0;
+    }
+    let total: f64 = counts.iter().sum();
+    let mut odds = [0.0; 11];
+    for value in 1..=10 {
+        odds[value] = counts[value] / total;
+    }
+    odds
+}
+
+fn dealer_outcomes(upcard: u8, odds: &Odds, rules: &TableRules) -> DealerOutcomes {
+    let mut outcomes = DealerOutcomes::default();
+    // The hole card can't complete a blackjack, the player is only deciding because there wasn't one
+    let mut hole_odds = *odds;
+    match upcard {
+        1 => hole_odds[10] = 0.0,
+        10 => hole_odds[1] = 0.0,
+        _ => {},
+    }
+    let total: f64 = hole_odds.iter().sum();
+    for (value, chance) in hole_odds.iter().enumerate().skip(1) {
+        if *chance > 0.0 {
+            dealer_draw(upcard + value as u8, upcard == 1 || value == 1, chance / total, odds, rules, &mut outcomes);
+        }
+    }
+    outcomes
+}
+
+// Plays the dealer's hand out the same way Round does, adding up the chance of every way it can end
+fn dealer_draw(hard: u8, ace: bool, chance: f64, odds: &Odds, rules: &TableRules, outcomes: &mut DealerOutcomes) {
+    let best = best_total(hard, ace);
+    let soft = best != hard;
+    if hard > 22 {
+        outcomes.bust += chance;
+    } else if hard == 22 {
+        outcomes.bust_22 += chance;
+    } else if best > 17 || (best == 17 && !(soft && rules.dealer_hits_soft_17)) {
+        outcomes.totals[(best - 17) as usize] += chance;
+    } else {
+        for value in 1..=10 {
+            dealer_draw(hard + value as u8, ace || value == 1, chance * odds[value], odds, rules, outcomes);
+        }
+    }
+}
+
+fn best_total(hard: u8, ace: bool) -> u8 {
+    if ace && hard + 10 <= 21 {
+        hard + 10
+    } else {
+        hard
+    }
+}
+
+// Whether the table lets the player double on this total
+fn double_allowed(hard: u8, ace: bool, rules: &TableRules) -> bool {
+    let total = best_total(hard, ace);
+    match rules.double_rule {
+        DoubleRule::AnyTwoCards => true,
+        DoubleRule::NineToEleven => (9..=11).contains(&total),
+        DoubleRule::TenToEleven => (10..=11).contains(&total),
+    }
+}
+
+// The player's side of the chart, hands are remembered by their hard total and whether they hold an ace
+struct PlayerValues<'a> {
+    odds: &'a Odds,
+    dealer: &'a DealerOutcomes,
+    rules: &'a TableRules,
+    best: [[Option<f64>; 2]; 22], // best of standing and hitting
+}
+
+impl<'a> PlayerValues<'a> {
+    fn new(odds: &'a Odds, dealer: &'a DealerOutcomes, rules: &'a TableRules) -> PlayerValues<'a> {
+        PlayerValues { odds, dealer, rules, best: [[None; 2]; 22] }
+    }
+
+    fn stand(&self, total: u8) -> f64 {
+        let dealer = self.dealer;
+        let mut value = dealer.bust;
+        value += if self.rules.dealer_22_pushes { 0.0 } else { dealer.bust_22 };
+        for (i, chance) in dealer.totals.iter().enumerate() {
+            let dealer_total = 17 + i as u8;
+            if total > dealer_total {
+                value += chance;
+            } else if total < dealer_total {
+                value -= chance;
+            }
+        }
+        value
+    }
+
+    fn hit(&mut self, hard: u8, ace: bool) -> f64 {
+        let mut value = 0.0;
+        for card in 1..=10 {
+            let next = hard + card as u8;
+            if next > 21 {
+                value -= self.odds[card];
+            } else {
+                value += self.odds[card] * self.best(next, ace || card == 1);
+            }
+        }
+        value
+    }
+
+    fn best(&mut self, hard: u8, ace: bool) -> f64 {
+        if let Some(value) = self.best[hard as usize][ace as usize] {
+            return value;
+        }
+        let value = self.stand(best_total(hard, ace)).max(self.hit(hard, ace));
+        self.best[hard as usize][ace as usize] = Some(value);
+        value
+    }
+
+    // Doubling takes exactly one more card for twice the bet
+    fn double(&self, hard: u8, ace: bool) -> f64 {
+        let mut value = 0.0;
+        for card in 1..=10 {
+            let next = hard + card as u8;
+            if next > 21 {
+                value -= self.odds[card];
+            } else {
+                value += self.odds[card] * self.stand(best_total(next, ace || card == 1));
+            }
+        }
+        2.0 * value
+    }
+
+    // One of the two hands after a split, played without splitting again
+    fn split_hand(&mut self, card: u8) -> f64 {
+        let split_aces_one_card = card == 1 && self.rules.split_aces_one_card;
+        let mut value = 0.0;
+        for second in 1..=10 {
+            let hard = card + second as u8;
+            let ace = card == 1 || second == 1;
+            let hand_value = if split_aces_one_card {
+                self.stand(best_total(hard, ace))
+            } else {
+                let mut best = self.best(hard, ace);
+                if self.rules.double_after_split && double_allowed(hard, ace, self.rules) {
+                    best = best.max(self.double(hard, ace));
+                }
+                best
+            };
+            value += self.odds[second] * hand_value;
+        }
+        value
+    }
+}
+
+fn card_value(card: &PlayingCard) -> u8 {
+    card.value.min(10)
+}
+
+fn upcard_index(card: &PlayingCard) -> usize {
+    match card_value(card) {
+        1 => 9,
+        value => value as usize - 2,
+    }
+}
+
+// Short name of an action for the advice overlay
+pub fn action_name(action: Action) -> &'static str {
+    match action {
+        Action::Hit => "Hit",
+        Action::Stand => "Stand",
+        Action::Double => "Double",
+        Action::Split => "Split",
+        Action::Surrender => "Surrender",
+        Action::Insurance(_) => "Insurance",
+        Action::EvenMoney => "Even Money",
+        Action::Decline => "Decline",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blackjack::engine::{CardSuit, PlayerHand};
+    use crate::blackjack::money::Money;
+
+    fn card(value: u8) -> PlayingCard {
+        PlayingCard { suit: CardSuit::Spade, value }
+    }
+
+    // The chart's advice for a starting hand against the upcard, with every action the rules allow on it
+    fn advice(rules: &TableRules, cards: [u8; 2], upcard: u8) -> Action {
+        let mut hand = PlayerHand::new(Money::from_units(10));
+        hand.cards = cards.iter().map(|value| card(*value)).collect();
+        let view = PlayerView {
+            phase: Phase::PlayerTurn,
+            hand: &hand,
+            hands: 1,
+            dealer_upcard: card(upcard),
+            seen: &[],
+            money: Money::from_units(100),
+            can_hit: true,
+            can_double: true,
+            can_split: cards[0] == cards[1],
+            can_surrender: rules.surrender != SurrenderRule::NoSurrender,
+            rules,
+        };
+        BasicStrategy::new(rules).advise(&view).unwrap()
+    }
+
+    #[test]
+    fn hard_16_against_10_surrenders_when_allowed() {
+        let late = TableRules { surrender: SurrenderRule::Late, ..TableRules::default() };
+        assert_eq!(advice(&late, [10, 6], 10), Action::Surrender);
+        let none = TableRules { surrender: SurrenderRule::NoSurrender, ..TableRules::default() };
+        assert_eq!(advice(&none, [10, 6], 10), Action::Hit);
+    }
+
+    #[test]
+    fn eleven_against_an_ace_doubles_only_when_the_dealer_hits_soft_17() {
+        let h17 = TableRules { dealer_hits_soft_17: true, ..TableRules::default() };
+        assert_eq!(advice(&h17, [5, 6], 1), Action::Double);
+        let s17 = TableRules { dealer_hits_soft_17: false, ..TableRules::default() };
+        assert_eq!(advice(&s17, [5, 6], 1), Action::Hit);
+    }
+
+    #[test]
+    fn soft_18_against_2() {
+        let s17 = TableRules { dealer_hits_soft_17: false, ..TableRules::default() };
+        assert_eq!(advice(&s17, [1, 7], 2), Action::Stand);
+        let h17 = TableRules { dealer_hits_soft_17: true, ..TableRules::default() };
+        assert_eq!(advice(&h17, [1, 7], 2), Action::Double);
+    }
+
+    #[test]
+    fn fours_against_5_split_only_with_double_after_split() {
+        let das = TableRules { double_after_split: true, ..TableRules::default() };
+        assert_eq!(advice(&das, [4, 4], 5), Action::Split);
+        let no_das = TableRules { double_after_split: false, ..TableRules::default() };
+        assert_eq!(advice(&no_das, [4, 4], 5), Action::Hit);
+    }
+
+    #[test]
+    fn rule_independent_cells() {
+        let rules = TableRules::default();
+        assert_eq!(advice(&rules, [8, 8], 10), Action::Split);
+        assert_eq!(advice(&rules, [1, 1], 6), Action::Split);
+        assert_eq!(advice(&rules, [10, 10], 6), Action::Stand);
+        assert_eq!(advice(&rules, [10, 2], 4), Action::Stand);
+        assert_eq!(advice(&rules, [10, 2], 3), Action::Hit);
+        assert_eq!(advice(&rules, [6, 4], 9), Action::Double);
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use super::engine::PlayingCard;
+
+// Card counting for the counting practice mode. The counter only sees the cards the player could see, in the order they
+// came out of the shoe, and starts over whenever a new shoe is shuffled.
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountingSystem {
+    HiLo,
+    KO,
+    OmegaII,
+    Zen,
+}
+
+pub struct CardCounter {
+    pub system: CountingSystem,
+    decks: usize,
+    seen: Vec<PlayingCard>, // every card that came out of the current shoe
+}
+
+impl CountingSystem {
+    // What a card adds to the running count
+    pub fn tag(&self, card: &PlayingCard) -> i32 {
+        let value = card.value.min(10);
+        match self {
+            CountingSystem::HiLo => match value {
+                2..=6 => 1,
+                7..=9 => 0,
+                _ => -1,
+            },
+            CountingSystem::KO => match value {
+                2..=7 => 1,
+                8 | 9 => 0,
+                _ => -1,
+            },
+            CountingSystem::OmegaII => match value {
+                2 | 3 | 7 => 1,
+                4..=6 => 2,
+                8 | 1 => 0,
+                9 => -1,
+                _ => -2,
+            },
+            CountingSystem::Zen => match value {
+                2 | 3 | 7 => 1,
+                4..=6 => 2,
+                8 | 9 => 0,
+                1 => -1,
+                _ => -2,
+            },
+        }
+    }
+
+    // A balanced count ends the shoe at zero and is divided by the decks left, KO is played off the running count
+    pub fn is_balanced(&self) -> bool {
+        *self != CountingSystem::KO
+    }
+
+    // KO starts below zero so that its running count can be used as it is
+    pub fn initial_count(&self, decks: usize) -> i32 {
+        match self {
+            CountingSystem::KO => 4 - 4 * decks as i32,
+            _ => 0,
+        }
+    }
+
+    // The count after the given cards came out of a fresh shoe
+    pub fn running_count(&self, seen: &[PlayingCard], decks: usize) -> i32 {
+        self.initial_count(decks) + seen.iter().map(|card| self.tag(card)).sum::<i32>()
+    }
+
+    // The running count per deck left for balanced systems, the running count itself for KO
+    pub fn true_count(&self, seen: &[PlayingCard], decks: usize) -> f32 {
+        if self.is_balanced() {
+            self.running_count(seen, decks) as f32 / decks_remaining(seen.len(), decks)
+        } else {
+            self.running_count(seen, decks) as f32
+        }
+    }
+
+    pub fn next(&self) -> CountingSystem {
+        match self {
+            CountingSystem::HiLo => CountingSystem::KO,
+            CountingSystem::KO => CountingSystem::OmegaII,
+            CountingSystem::OmegaII => CountingSystem::Zen,
+            CountingSystem::Zen => CountingSystem::HiLo,
+        }
+    }
+}
+
+impl fmt::Display for CountingSystem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CountingSystem::HiLo => write!(f, "Hi-Lo"),
+            CountingSystem::KO => write!(f, "KO"),
+            CountingSystem::OmegaII => write!(f, "Omega II"),
+            CountingSystem::Zen => write!(f, "Zen"),
+        }
+    }
+}
+
+impl CardCounter {
+    pub fn new(system: CountingSystem, decks: usize) -> CardCounter {
+        CardCounter { system, decks, seen: Vec::new() }
+    }
+
+    pub fn see(&mut self, card: PlayingCard) {
+        self.seen.push(card);
+    }
+
+    // A new shoe was shuffled
+    pub fn reset(&mut self) {
+        self.seen.clear();
+    }
+
+    pub fn seen(&self) -> &[PlayingCard] {
+        &self.seen
+    }
+
+    pub fn running_count(&self) -> i32 {
+        self.system.running_count(&self.seen, self.decks)
+    }
+
+    pub fn decks_remaining(&self) -> f32 {
+        decks_remaining(self.seen.len(), self.decks)
+    }
+
+    pub fn true_count(&self) -> f32 {
+        self.system.true_count(&self.seen, self.decks)
+    }
+}
+
+fn decks_remaining(seen: usize, decks: usize) -> f32 {
+    let cards_left = (decks * 52).saturating_sub(seen);
+    // Never divide by less than half a deck, the end of the shoe is behind the cut card anyway
+    (cards_left as f32 / 52.0).max(0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::engine::CardSuit;
+
+    const SYSTEMS: [CountingSystem; 4] = [CountingSystem::HiLo, CountingSystem::KO, CountingSystem::OmegaII, CountingSystem::Zen];
+
+    fn deck() -> Vec<PlayingCard> {
+        [CardSuit::Heart, CardSuit::Diamond, CardSuit::Spade, CardSuit::Club].iter()
+            .flat_map(|suit| (1..=13).map(move |value| PlayingCard { suit: *suit, value }))
+            .collect()
+    }
+
+    fn cards(value: u8, count: usize) -> Vec<PlayingCard> {
+        vec![PlayingCard { suit: CardSuit::Spade, value }; count]
+    }
+
+    #[test]
+    fn a_full_deck_adds_up_to_zero_for_balanced_systems_and_four_for_ko() {
+        for system in SYSTEMS {
+            let total: i32 = deck().iter().map(|card| system.tag(card)).sum();
+            let expected = if system == CountingSystem::KO { 4 } else { 0 };
+            assert_eq!(total, expected, "{}", system);
+            assert_eq!(system.is_balanced(), expected == 0);
+        }
+    }
+
+    #[test]
+    fn ko_starts_below_zero_and_ends_the_shoe_at_four() {
+        for decks in [1, 2, 6, 8] {
+            assert_eq!(CountingSystem::KO.initial_count(decks), 4 - 4 * decks as i32);
+            let shoe: Vec<PlayingCard> = (0..decks).flat_map(|_| deck()).collect();
+            assert_eq!(CountingSystem::KO.running_count(&shoe, decks), 4);
+            assert_eq!(CountingSystem::HiLo.running_count(&shoe, decks), 0);
+        }
+    }
+
+    #[test]
+    fn true_count_divides_by_the_decks_left() {
+        // 52 low cards out of two decks leave one deck, 26 leave a deck and a half
+        assert_eq!(CountingSystem::HiLo.true_count(&cards(2, 52), 2), 52.0);
+        assert_eq!(CountingSystem::HiLo.true_count(&cards(5, 26), 2), 26.0 / 1.5);
+        assert_eq!(CountingSystem::OmegaII.true_count(&cards(5, 26), 2), 52.0 / 1.5);
+        // KO is used as it is
+        assert_eq!(CountingSystem::KO.true_count(&cards(2, 52), 2), 48.0);
+    }
+
+    #[test]
+    fn true_count_never_divides_by_less_than_half_a_deck() {
+        let counter = CardCounter { system: CountingSystem::HiLo, decks: 1, seen: cards(3, 50) };
+        assert_eq!(counter.decks_remaining(), 0.5);
+        assert_eq!(counter.true_count(), 100.0);
+    }
+}
@@ -0,0 +1,1273 @@
+use std::{fmt, cmp};
+use rand::{prelude::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::Deserialize;
+use super::money::Money;
+use super::rules::{TableRules, DoubleRule, HoleCardRule, SurrenderRule, SideBetCards, SideBetSettle, Condition};
+
+// The blackjack rules without any rendering or input. A Round is driven with actions and answers with the events that
+// happened at the table, so the plugin, the tools and the tests all play by the same code.
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PlayingCard {
+    pub suit: CardSuit,
+    pub value: u8,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+pub enum CardSuit {
+    Heart,
+    Diamond,
+    Spade,
+    Club,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CardColour {
+    Red,
+    Black,
+}
+
+// Every shuffle and draw goes through one seeded generator, so the same seed deals the same shoes in the same order
+pub type ShoeRng = ChaCha8Rng;
+
+// Cards are dealt from the top of the shoe, which is only reshuffled after the round where the cut card comes out
+pub struct Shoe {
+    pub cards: Vec<PlayingCard>,
+    pub discards: Vec<PlayingCard>,
+    pub cut_card: usize, // how many cards are left in the shoe behind the cut card
+}
+
+#[derive(Debug, Clone)]
+pub struct PlayerHand {
+    pub cards: Vec<PlayingCard>,
+    pub bet: Money,
+    pub doubled: bool,
+    pub stood: bool,
+    pub from_split: bool,
+    pub even_money: bool,
+    pub surrendered: bool,
+}
+
+// Everything the rules look at in a hand's cards
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandEval {
+    pub hard_total: u8, // every ace counted as 1
+    pub best_total: u8, // one ace counted as 11 when that doesn't bust the hand
+    pub is_soft: bool,
+    pub is_blackjack: bool,
+    pub is_bust: bool,
+    pub cards: usize,
+}
+
+// What the round is waiting on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    EarlySurrender,
+    Insurance,
+    EvenMoney,
+    PlayerTurn,
+    Finished,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Hit,
+    Stand,
+    Double,
+    Split,
+    Surrender,
+    Insurance(Money),
+    EvenMoney,
+    Decline, // turns down early surrender, insurance or even money
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionError {
+    NotEnoughMoney,
+    NotAllowed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Blackjack,
+    Push,
+    Lose,
+    Surrendered,
+    EvenMoney,
+}
+
+// Everything a seat put on the table during the round and everything that came back
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settlement {
+    pub outcomes: Vec<Outcome>,
+    pub wagered: Money,
+    pub returned: Money,
+}
+
+// Events about a player's cards and bets carry the seat they happened at
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    PlayerCard { seat: usize, hand: usize, card: PlayingCard },
+    DealerCard { card: PlayingCard, face_down: bool },
+    HoleCardRevealed(PlayingCard),
+    HandSplit { seat: usize, hand: usize, new_hand: usize }, // the second card of hand moved over to new_hand
+    DealerChecks,
+    DealerBlackjack,
+    PlayerBlackjack { seat: usize, hand: usize },
+    PlayerBust { seat: usize, hand: usize },
+    DealerHits,
+    DealerStands,
+    DealerBust,
+    SideBetSettled { seat: usize, side_bet: usize, line: Option<usize>, returned: Money },
+    HandSettled { seat: usize, hand: usize, outcome: Outcome, returned: Money },
+    InsuranceSettled { seat: usize, returned: Money },
+    CutCardReached,
+    DiscardsReshuffled,
+    RoundOver(Vec<Settlement>), // one settlement per seat, empty for a seat that sat the round out
+}
+
+// What a seat puts on the table before the cards come out, a seat without a bet sits the round out
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeatBet {
+    pub bet: Money,
+    pub side_bets: Vec<Money>, // wager on each of the table's side bets
+}
+
+// One player's place at the table. Every seat plays against the same dealer hand, in seat order.
+pub struct Seat {
+    pub hands: Vec<PlayerHand>,
+    pub active_hand: usize,
+    pub insurance: Money,
+    pub side_bets: Vec<Money>,
+    wagered: Money,
+    returned: Money,
+}
+
+pub struct Round {
+    pub seats: Vec<Seat>,
+    pub active_seat: usize, // the seat the round is waiting on
+    pub dealer_hand: Vec<PlayingCard>,
+    pub hole_card_hidden: bool,
+    pub phase: Phase,
+}
+
+//to_string for CardSuit
+impl fmt::Display for CardSuit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CardSuit::Heart => write!(f, "Heart"),
+            CardSuit::Diamond => write!(f, "Diamond"),
+            CardSuit::Spade => write!(f, "Spade"),
+            CardSuit::Club => write!(f, "Club"),
+        }
+    }
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActionError::NotEnoughMoney => write!(f, "Not enough money for this bet!"),
+            ActionError::NotAllowed => write!(f, "That isn't allowed right now!"),
+        }
+    }
+}
+
+impl CardSuit {
+    pub fn colour(&self) -> CardColour {
+        match self {
+            CardSuit::Heart | CardSuit::Diamond => CardColour::Red,
+            CardSuit::Spade | CardSuit::Club => CardColour::Black,
+        }
+    }
+}
+
+impl HandEval {
+    // A two card 21 is only a blackjack on a hand that wasn't split
+    pub fn new(cards: &[PlayingCard], from_split: bool) -> HandEval {
+        let hard_total: u8 = cards.iter().map(|card| cmp::min(card.value, 10)).sum();
+        let is_soft = cards.iter().any(|card| card.value == 1) && hard_total + 10 <= 21;
+        let best_total = if is_soft { hard_total + 10 } else { hard_total };
+        HandEval {
+            hard_total,
+            best_total,
+            is_soft,
+            is_blackjack: cards.len() == 2 && !from_split && best_total == 21,
+            is_bust: hard_total > 21,
+            cards: cards.len(),
+        }
+    }
+
+    pub fn of(cards: &[PlayingCard]) -> HandEval {
+        HandEval::new(cards, false)
+    }
+}
+
+// Soft totals show both ways of counting the ace, like 7/17
+impl fmt::Display for HandEval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_soft && self.best_total < 21 {
+            write!(f, "{}/{}", self.hard_total, self.best_total)
+        } else {
+            write!(f, "{}", self.best_total)
+        }
+    }
+}
+
+impl PlayerHand {
+    pub fn eval(&self) -> HandEval {
+        HandEval::new(&self.cards, self.from_split)
+    }
+
+    pub fn new(bet: Money) -> PlayerHand {
+        PlayerHand {
+            cards: Vec::new(),
+            bet,
+            doubled: false,
+            stood: false,
+            from_split: false,
+            even_money: false,
+            surrendered: false,
+        }
+    }
+}
+
+impl Shoe {
+    pub fn cut_card_reached(&self) -> bool {
+        self.cards.len() <= self.cut_card
+    }
+}
+
+impl Settlement {
+    // What the round won or lost the player
+    pub fn net(&self) -> Money {
+        self.returned - self.wagered
+    }
+}
+
+impl Seat {
+    // Whether the seat has a bet in this round
+    pub fn is_playing(&self) -> bool {
+        !self.hands.is_empty()
+    }
+
+    // The first split always moves the seat's second card to the second hand
+    fn first_two(&self) -> Vec<PlayingCard> {
+        if self.hands.len() > 1 {
+            vec![self.hands[0].cards[0], self.hands[1].cards[0]]
+        } else {
+            self.hands[0].cards[..2].to_vec()
+        }
+    }
+}
+
+impl Round {
+    // Takes every seat's bet and side bets from its bankroll and deals the starting cards, one card to each seat in turn
+    // and then the dealer, twice round the table
+    pub fn start(bets: Vec<SeatBet>, bankrolls: &mut [Money], shoe: &mut Shoe, rng: &mut ShoeRng, rules: &TableRules) -> Result<(Round, Vec<Event>), ActionError> {
+        if bets.iter().all(|seat| !seat.bet.is_positive()) {
+            return Err(ActionError::NotAllowed);
+        }
+        if bets.iter().flat_map(|seat| seat.side_bets.iter()).any(|wager| *wager > rules.max_side_bet) {
+            return Err(ActionError::NotAllowed);
+        }
+        for (seat, money) in bets.iter().zip(bankrolls.iter()) {
+            if seat.bet.is_positive() && seat.bet + seat.side_bets.iter().sum::<Money>() > *money {
+                return Err(ActionError::NotEnoughMoney);
+            }
+        }
+        let mut round = Round {
+            seats: Vec::new(),
+            active_seat: 0,
+            dealer_hand: Vec::new(),
+            hole_card_hidden: false,
+            phase: Phase::PlayerTurn,
+        };
+        for (i, bet) in bets.into_iter().enumerate() {
+            let mut seat = Seat { hands: Vec::new(), active_hand: 0, insurance: Money::ZERO, side_bets: Vec::new(), wagered: Money::ZERO, returned: Money::ZERO };
+            if bet.bet.is_positive() {
+                let cost = bet.bet + bet.side_bets.iter().sum::<Money>();
+                bankrolls[i] -= cost;
+                seat.wagered = cost;
+                seat.hands.push(PlayerHand::new(bet.bet));
+                seat.side_bets = bet.side_bets;
+            }
+            round.seats.push(seat);
+        }
+        let mut events = Vec::new();
+        for _ in 0..2 {
+            for i in 0..round.seats.len() {
+                if round.seats[i].is_playing() {
+                    round.active_seat = i;
+                    round.deal_player(shoe, rng, &mut events);
+                }
+            }
+            // Without a hole card the dealer only gets the upcard before the players' turns
+            if round.dealer_hand.is_empty() {
+                round.deal_dealer(false, shoe, rng, &mut events);
+            } else if rules.hole_card == HoleCardRule::Peek {
+                round.deal_dealer(true, shoe, rng, &mut events);
+            }
+        }
+        // Side bets on the first cards are settled before the players' turns
+        for i in 0..round.seats.len() {
+            round.settle_side_bets(i, SideBetSettle::AfterDeal, bankrolls, rules, &mut events);
+        }
+        // Early surrender has to be offered before the dealer can reveal a blackjack
+        if rules.surrender == SurrenderRule::Early && upcard_can_blackjack(&round.dealer_hand) {
+            round.offer_early_surrender(0, bankrolls, shoe, rng, rules, &mut events);
+        } else {
+            round.offer_insurance(0, bankrolls, shoe, rng, rules, &mut events);
+        }
+        Ok((round, events))
+    }
+
+    // Plays the active seat's decision, then everything up to the next decision or the end of the round
+    pub fn act(&mut self, action: Action, bankrolls: &mut [Money], shoe: &mut Shoe, rng: &mut ShoeRng, rules: &TableRules) -> Result<Vec<Event>, ActionError> {
+        let mut events = Vec::new();
+        let seat = self.active_seat;
+        let money = bankrolls[seat];
+        match (self.phase, action) {
+            (Phase::EarlySurrender, Action::Surrender) => {
+                self.seats[seat].hands[0].surrendered = true;
+                self.offer_early_surrender(seat + 1, bankrolls, shoe, rng, rules, &mut events);
+            },
+            (Phase::EarlySurrender, Action::Decline) => self.offer_early_surrender(seat + 1, bankrolls, shoe, rng, rules, &mut events),
+            (Phase::Insurance, Action::Insurance(amount)) => {
+                if amount.is_negative() || amount > rules.max_insurance(self.seats[seat].hands[0].bet) {
+                    return Err(ActionError::NotAllowed);
+                }
+                if amount > money {
+                    return Err(ActionError::NotEnoughMoney);
+                }
+                self.wager(seat, amount, bankrolls);
+                self.seats[seat].insurance = amount;
+                self.offer_insurance(seat + 1, bankrolls, shoe, rng, rules, &mut events);
+            },
+            (Phase::Insurance, Action::Decline) | (Phase::EvenMoney, Action::Decline) => self.offer_insurance(seat + 1, bankrolls, shoe, rng, rules, &mut events),
+            (Phase::EvenMoney, Action::EvenMoney) => {
+                self.seats[seat].hands[0].even_money = true;
+                self.offer_insurance(seat + 1, bankrolls, shoe, rng, rules, &mut events);
+            },
+            (Phase::PlayerTurn, Action::Hit) => {
+                if !self.can_hit(rules) {
+                    return Err(ActionError::NotAllowed);
+                }
+                self.deal_player(shoe, rng, &mut events);
+                self.play_on(bankrolls, shoe, rng, rules, &mut events);
+            },
+            (Phase::PlayerTurn, Action::Stand) => {
+                self.current_hand_mut().stood = true;
+                self.play_on(bankrolls, shoe, rng, rules, &mut events);
+            },
+            (Phase::PlayerTurn, Action::Double) => {
+                if !self.can_double(money, rules) {
+                    return Err(self.refusal(money));
+                }
+                let bet = self.current_hand().bet;
+                self.wager(seat, bet, bankrolls);
+                let hand = self.current_hand_mut();
+                hand.bet += bet;
+                hand.doubled = true;
+                self.deal_player(shoe, rng, &mut events);
+                self.play_on(bankrolls, shoe, rng, rules, &mut events);
+            },
+            (Phase::PlayerTurn, Action::Split) => {
+                if !self.can_split(money, rules) {
+                    return Err(self.refusal(money));
+                }
+                let bet = self.current_hand().bet;
+                self.wager(seat, bet, bankrolls);
+                let new_hand = self.seats[seat].hands.len();
+                let card = self.current_hand_mut().cards.pop().unwrap();
+                let mut split_hand = PlayerHand::new(bet);
+                split_hand.cards.push(card);
+                split_hand.from_split = true;
+                self.current_hand_mut().from_split = true;
+                self.seats[seat].hands.push(split_hand);
+                events.push(Event::HandSplit { seat, hand: self.seats[seat].active_hand, new_hand });
+                self.deal_player(shoe, rng, &mut events);
+                self.play_on(bankrolls, shoe, rng, rules, &mut events);
+            },
+            (Phase::PlayerTurn, Action::Surrender) => {
+                if !self.can_surrender(rules) {
+                    return Err(ActionError::NotAllowed);
+                }
+                self.current_hand_mut().surrendered = true;
+                self.play_on(bankrolls, shoe, rng, rules, &mut events);
+            },
+            _ => return Err(ActionError::NotAllowed),
+        }
+        Ok(events)
+    }
+
+    // Played cards go to the discard tray, the shoe is only replaced once the cut card has come out. Returns whether a new shoe was shuffled.
+    pub fn clear(self, shoe: &mut Shoe, rng: &mut ShoeRng, rules: &TableRules) -> bool {
+        for seat in self.seats {
+            for hand in seat.hands {
+                shoe.discards.extend(hand.cards);
+            }
+        }
+        shoe.discards.extend(self.dealer_hand);
+        if shoe.cut_card_reached() {
+            *shoe = init_shoe(rules.decks, rules.penetration, rng);
+            return true;
+        }
+        false
+    }
+
+    pub fn current_seat(&self) -> &Seat {
+        &self.seats[self.active_seat]
+    }
+
+    pub fn current_hand(&self) -> &PlayerHand {
+        let seat = self.current_seat();
+        &seat.hands[seat.active_hand]
+    }
+
+    fn current_hand_mut(&mut self) -> &mut PlayerHand {
+        let seat = &mut self.seats[self.active_seat];
+        &mut seat.hands[seat.active_hand]
+    }
+
+    // A split ace that drew another ace is only left open to be split again, it can't take a card of its own
+    pub fn can_hit(&self, rules: &TableRules) -> bool {
+        !self.one_card_split_ace(rules)
+    }
+
+    // Doubling is only allowed on the first two cards, with enough money for a second bet and a total allowed by the table rules
+    pub fn can_double(&self, money: Money, rules: &TableRules) -> bool {
+        let hand = self.current_hand();
+        if hand.cards.len() != 2 || hand.doubled || hand.bet > money || self.one_card_split_ace(rules) {
+            return false;
+        }
+        if hand.from_split && !rules.double_after_split {
+            return false;
+        }
+        match rules.double_rule {
+            DoubleRule::AnyTwoCards => true,
+            DoubleRule::NineToEleven => (9..=11).contains(&hand.eval().best_total),
+            DoubleRule::TenToEleven => (10..=11).contains(&hand.eval().best_total),
+        }
+    }
+
+    // Pairs can be split as long as the table's hand limit and the player's money allow another bet
+    pub fn can_split(&self, money: Money, rules: &TableRules) -> bool {
+        let hand = self.current_hand();
+        if hand.cards.len() != 2 || hand.cards[0].value != hand.cards[1].value || hand.bet > money {
+            return false;
+        }
+        if self.current_seat().hands.len() >= rules.max_split_hands {
+            return false;
+        }
+        if hand.from_split && hand.cards[0].value == 1 && !rules.resplit_aces {
+            return false;
+        }
+        true
+    }
+
+    // Surrender is only offered as the first decision on the starting hand
+    pub fn can_surrender(&self, rules: &TableRules) -> bool {
+        let hand = self.current_hand();
+        rules.surrender != SurrenderRule::NoSurrender && self.current_seat().hands.len() == 1 && hand.cards.len() == 2 && !hand.even_money
+    }
+
+    // With split_aces_one_card a hand split from aces gets one card and no more
+    fn one_card_split_ace(&self, rules: &TableRules) -> bool {
+        let hand = self.current_hand();
+        hand.from_split && hand.cards[0].value == 1 && rules.split_aces_one_card
+    }
+
+    // A hand is finished once the player can't take any more actions on it
+    fn hand_finished(&self, money: Money, rules: &TableRules) -> bool {
+        let hand = self.current_hand();
+        if hand.cards.len() < 2 {
+            return false;
+        }
+        let split_aces = self.one_card_split_ace(rules);
+        hand.stood
+            || hand.surrendered
+            || hand.eval().is_blackjack
+            || hand.eval().is_bust
+            || (hand.doubled && hand.cards.len() > 2) // doubling down only gets one card
+            || (split_aces && !self.can_split(money, rules))
+    }
+
+    // Tells a double or split that was turned down for money apart from one the rules don't allow
+    fn refusal(&self, money: Money) -> ActionError {
+        if self.current_hand().bet > money {
+            ActionError::NotEnoughMoney
+        } else {
+            ActionError::NotAllowed
+        }
+    }
+
+    // The next seat from the given one that still has a starting hand to decide on, blackjacks and surrendered hands are skipped
+    fn next_seat_to_ask(&self, from: usize) -> Option<usize> {
+        (from..self.seats.len()).find(|i| {
+            let seat = &self.seats[*i];
+            seat.is_playing() && !seat.hands[0].surrendered
+        })
+    }
+
+    // Every seat without a blackjack is asked in turn, the insurance offers come after
+    fn offer_early_surrender(&mut self, from: usize, bankrolls: &mut [Money], shoe: &mut Shoe, rng: &mut ShoeRng, rules: &TableRules, events: &mut Vec<Event>) {
+        let next = (from..self.seats.len()).find(|i| {
+            let seat = &self.seats[*i];
+            seat.is_playing() && !seat.hands[0].eval().is_blackjack
+        });
+        match next {
+            Some(seat) => {
+                self.active_seat = seat;
+                self.phase = Phase::EarlySurrender;
+            },
+            None => self.offer_insurance(0, bankrolls, shoe, rng, rules, events),
+        }
+    }
+
+    // An ace upcard gets every seat an insurance offer, or even money for a blackjack, before the dealer peeks
+    fn offer_insurance(&mut self, from: usize, bankrolls: &mut [Money], shoe: &mut Shoe, rng: &mut ShoeRng, rules: &TableRules, events: &mut Vec<Event>) {
+        let next = if rules.insurance && self.dealer_hand[0].value == 1 { self.next_seat_to_ask(from) } else { None };
+        match next {
+            Some(seat) => {
+                self.active_seat = seat;
+                if self.seats[seat].hands[0].eval().is_blackjack {
+                    self.phase = Phase::EvenMoney;
+                } else {
+                    self.phase = Phase::Insurance;
+                }
+            },
+            None => self.peek(bankrolls, shoe, rng, rules, events),
+        }
+    }
+
+    fn peek(&mut self, bankrolls: &mut [Money], shoe: &mut Shoe, rng: &mut ShoeRng, rules: &TableRules, events: &mut Vec<Event>) {
+        // Once every seat surrendered early there's nothing left to play for, apart from a side bet on the dealer's hand
+        if self.next_seat_to_ask(0).is_none() {
+            if self.dealer_hand_wagered(rules) {
+                self.dealer_play(shoe, rng, rules, events);
+            }
+            self.finish(bankrolls, rules, events);
+            return;
+        }
+        if rules.hole_card == HoleCardRule::Peek && upcard_can_blackjack(&self.dealer_hand) {
+            if HandEval::of(&self.dealer_hand).is_blackjack {
+                events.push(Event::DealerBlackjack);
+                self.finish(bankrolls, rules, events);
+                return;
+            }
+            events.push(Event::DealerChecks);
+        }
+        self.phase = Phase::PlayerTurn;
+        self.active_seat = 0;
+        self.play_on(bankrolls, shoe, rng, rules, events);
+    }
+
+    // Deals split hands their second card and moves past finished hands and seats, the dealer plays once every seat is done
+    fn play_on(&mut self, bankrolls: &mut [Money], shoe: &mut Shoe, rng: &mut ShoeRng, rules: &TableRules, events: &mut Vec<Event>) {
+        while self.active_seat < self.seats.len() {
+            let seat = self.current_seat();
+            if seat.active_hand >= seat.hands.len() {
+                self.active_seat += 1; // this seat is done or sat the round out
+            } else if self.current_hand().cards.len() < 2 {
+                self.deal_player(shoe, rng, events); // split hands only have one card at this point
+            } else if self.hand_finished(bankrolls[self.active_seat], rules) {
+                let hand = self.current_hand();
+                let eval = hand.eval();
+                let (seat, hand) = (self.active_seat, seat.active_hand);
+                if eval.is_blackjack {
+                    events.push(Event::PlayerBlackjack { seat, hand });
+                } else if eval.is_bust {
+                    events.push(Event::PlayerBust { seat, hand });
+                }
+                self.seats[seat].active_hand += 1;
+            } else {
+                return; // waiting on the player
+            }
+        }
+        if self.dealer_must_play(rules) {
+            self.dealer_play(shoe, rng, rules, events);
+        }
+        self.finish(bankrolls, rules, events);
+    }
+
+    fn hands(&self) -> impl Iterator<Item = &PlayerHand> {
+        self.seats.iter().flat_map(|seat| seat.hands.iter())
+    }
+
+    // Whether a seat has a wager on a side bet that is settled on the dealer's finished hand
+    fn dealer_hand_wagered(&self, rules: &TableRules) -> bool {
+        self.seats.iter().filter(|seat| seat.is_playing()).any(|seat| {
+            rules.side_bets.iter().zip(seat.side_bets.iter()).any(|(side_bet, wager)| wager.is_positive() && side_bet.needs_dealer_hand())
+        })
+    }
+
+    // The dealer plays out when a hand is still standing, when a side bet is on the dealer's hand, or without a hole card
+    // when a late surrender or insurance depends on a possible blackjack
+    fn dealer_must_play(&self, rules: &TableRules) -> bool {
+        if self.dealer_hand_wagered(rules) {
+            return true;
+        }
+        let blackjack_unknown = rules.hole_card == HoleCardRule::NoHoleCard && upcard_can_blackjack(&self.dealer_hand);
+        // A blackjack is already paid unless the dealer has one too, which only the second card can tell
+        self.hands().any(|hand| {
+            let eval = hand.eval();
+            if eval.is_blackjack || hand.even_money {
+                return blackjack_unknown;
+            }
+            !eval.is_bust && (!hand.surrendered || (blackjack_unknown && rules.surrender == SurrenderRule::Late))
+        }) || (blackjack_unknown && self.seats.iter().any(|seat| seat.insurance.is_positive()))
+    }
+
+    fn dealer_play(&mut self, shoe: &mut Shoe, rng: &mut ShoeRng, rules: &TableRules, events: &mut Vec<Event>) {
+        self.reveal_hole_card(events);
+        loop {
+            let dealer = HandEval::of(&self.dealer_hand);
+            let hits_soft_17 = rules.dealer_hits_soft_17 && dealer.best_total == 17 && dealer.is_soft;
+            // A blackjack, a late surrender or insurance only needs the dealer's second card when there's no hand left for the
+            // dealer to beat and no side bet on the dealer's hand
+            let hands_in_play = self.hands().any(|hand| !hand.surrendered && !hand.eval().is_blackjack && !hand.eval().is_bust)
+                || self.dealer_hand_wagered(rules);
+            if dealer.is_blackjack {
+                events.push(Event::DealerBlackjack);
+                return;
+            } else if dealer.is_bust {
+                events.push(Event::DealerBust);
+                return;
+            } else if (dealer.best_total >= 17 && !hits_soft_17) || (self.dealer_hand.len() >= 2 && !hands_in_play) {
+                events.push(Event::DealerStands);
+                return;
+            }
+            events.push(Event::DealerHits);
+            self.deal_dealer(false, shoe, rng, events);
+        }
+    }
+
+    // Settles every seat in turn
+    fn finish(&mut self, bankrolls: &mut [Money], rules: &TableRules, events: &mut Vec<Event>) {
+        self.reveal_hole_card(events);
+        let mut settlements = Vec::new();
+        for seat in 0..self.seats.len() {
+            let mut outcomes = Vec::new();
+            for i in 0..self.seats[seat].hands.len() {
+                let (outcome, returned) = settle_hand(&self.seats[seat].hands[i], &self.dealer_hand, rules);
+                self.pay(seat, returned, bankrolls);
+                outcomes.push(outcome);
+                events.push(Event::HandSettled { seat, hand: i, outcome, returned });
+            }
+
+            // Insurance pays 2:1 when the dealer has blackjack
+            let insurance = self.seats[seat].insurance;
+            if insurance.is_positive() {
+                let returned = if HandEval::of(&self.dealer_hand).is_blackjack { insurance * 3 } else { Money::ZERO };
+                self.pay(seat, returned, bankrolls);
+                events.push(Event::InsuranceSettled { seat, returned });
+            }
+
+            self.settle_side_bets(seat, SideBetSettle::GameEnd, bankrolls, rules, events);
+            settlements.push(Settlement { outcomes, wagered: self.seats[seat].wagered, returned: self.seats[seat].returned });
+        }
+        self.phase = Phase::Finished;
+        events.push(Event::RoundOver(settlements));
+    }
+
+    // Pays the first paytable line whose conditions all hold for each of the seat's side bets settled at this point of the round
+    fn settle_side_bets(&mut self, seat: usize, settle: SideBetSettle, bankrolls: &mut [Money], rules: &TableRules, events: &mut Vec<Event>) {
+        if !self.seats[seat].is_playing() {
+            return;
+        }
+        for (i, side_bet) in rules.side_bets.iter().enumerate() {
+            let wager = self.seats[seat].side_bets.get(i).copied().unwrap_or(Money::ZERO);
+            if side_bet.settle != settle || !wager.is_positive() {
+                continue;
+            }
+            let cards = self.side_bet_cards(seat, side_bet.cards);
+            let line = side_bet.paytable.iter().position(|line| {
+                line.when.iter().all(|condition| condition_holds(condition, &cards, &self.dealer_hand))
+            });
+            let returned = match line {
+                Some(line) => wager * (side_bet.paytable[line].pays as i64 + 1),
+                None => Money::ZERO,
+            };
+            self.pay(seat, returned, bankrolls);
+            events.push(Event::SideBetSettled { seat, side_bet: i, line, returned });
+        }
+    }
+
+    fn side_bet_cards(&self, seat: usize, cards: SideBetCards) -> Vec<PlayingCard> {
+        let first_two = self.seats[seat].first_two();
+        match cards {
+            SideBetCards::FirstTwo => first_two,
+            SideBetCards::FirstTwoAndUpcard => vec![first_two[0], first_two[1], self.dealer_hand[0]],
+            SideBetCards::DealerHand => self.dealer_hand.clone(),
+        }
+    }
+
+    fn reveal_hole_card(&mut self, events: &mut Vec<Event>) {
+        if self.hole_card_hidden {
+            self.hole_card_hidden = false;
+            events.push(Event::HoleCardRevealed(self.dealer_hand[1]));
+        }
+    }
+
+    fn deal_player(&mut self, shoe: &mut Shoe, rng: &mut ShoeRng, events: &mut Vec<Event>) {
+        let card = draw(shoe, rng, events);
+        self.current_hand_mut().cards.push(card);
+        events.push(Event::PlayerCard { seat: self.active_seat, hand: self.current_seat().active_hand, card });
+    }
+
+    // The dealer's second card of the initial deal is dealt face down
+    fn deal_dealer(&mut self, face_down: bool, shoe: &mut Shoe, rng: &mut ShoeRng, events: &mut Vec<Event>) {
+        let card = draw(shoe, rng, events);
+        self.dealer_hand.push(card);
+        self.hole_card_hidden = self.hole_card_hidden || face_down;
+        events.push(Event::DealerCard { card, face_down });
+    }
+
+    fn wager(&mut self, seat: usize, amount: Money, bankrolls: &mut [Money]) {
+        bankrolls[seat] -= amount;
+        self.seats[seat].wagered += amount;
+    }
+
+    fn pay(&mut self, seat: usize, amount: Money, bankrolls: &mut [Money]) {
+        bankrolls[seat] += amount;
+        self.seats[seat].returned += amount;
+    }
+}
+
+fn draw(shoe: &mut Shoe, rng: &mut ShoeRng, events: &mut Vec<Event>) -> PlayingCard {
+    if shoe.cards.is_empty() { // a deep cut card can run a small shoe dry mid round
+        events.push(Event::DiscardsReshuffled);
+    }
+    let card = get_card(shoe, rng);
+    if shoe.cards.len() == shoe.cut_card {
+        events.push(Event::CutCardReached);
+    }
+    card
+}
+
+pub fn seeded_rng(seed: u64) -> ShoeRng {
+    ShoeRng::seed_from_u64(seed)
+}
+
+// A fresh seed for when none was asked for, it's shown to the player so the session can be replayed
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen()
+}
+
+pub fn init_deck() -> Vec<PlayingCard> {
+    let mut deck = Vec::new();
+    let mut current_suit = CardSuit::Heart;
+    for i in 1..53 {
+
+        if i % 13 == 0 {
+            deck.push(PlayingCard { suit: current_suit, value: 13});
+            match current_suit {
+                CardSuit::Heart => current_suit = CardSuit::Diamond,
+                CardSuit::Diamond => current_suit = CardSuit::Spade,
+                CardSuit::Spade => current_suit = CardSuit::Club,
+                CardSuit::Club => current_suit = CardSuit::Heart,
+            }
+        }
+        else {
+            deck.push(PlayingCard { suit: current_suit, value: i % 13});
+        }
+    }
+
+    deck
+}
+
+// Shuffles the given number of decks together, the cut card is placed so that the penetration share of the shoe gets dealt
+pub fn init_shoe(decks: usize, penetration: f32, rng: &mut ShoeRng) -> Shoe {
+    let mut cards = Vec::new();
+    for _ in 0..decks {
+        cards.append(&mut init_deck());
+    }
+
+    cards.shuffle(rng);
+
+    let cut_card = cards.len() - (cards.len() as f32 * penetration) as usize;
+    Shoe { cards, discards: Vec::new(), cut_card }
+}
+
+pub fn get_card(shoe: &mut Shoe, rng: &mut ShoeRng) -> PlayingCard {
+    if shoe.cards.is_empty() {
+        shoe.cards.append(&mut shoe.discards);
+        shoe.cards.shuffle(rng);
+    }
+    shoe.cards.pop().unwrap()
+}
+
+// Only an ace or a ten valued upcard can turn into a dealer blackjack
+pub fn upcard_can_blackjack(dealer_hand: &[PlayingCard]) -> bool {
+    dealer_hand[0].value == 1 || cmp::min(dealer_hand[0].value, 10) == 10
+}
+
+// Returns the outcome of a hand and what the player gets back from its bet after comparing it to the dealer's hand
+pub fn settle_hand(hand: &PlayerHand, dealer_hand: &[PlayingCard], rules: &TableRules) -> (Outcome, Money) {
+    let player = hand.eval();
+    let dealer = HandEval::of(dealer_hand);
+    if hand.even_money {
+        (Outcome::EvenMoney, hand.bet * 2) //Player took a guaranteed 1:1 payout for their blackjack
+    } else if hand.surrendered {
+        if rules.surrender == SurrenderRule::Late && dealer.is_blackjack {
+            (Outcome::Lose, Money::ZERO) //Late surrender doesn't save the bet from a dealer blackjack
+        } else {
+            (Outcome::Surrendered, hand.bet.mul_ratio(1, 2, rules.payout_rounding)) //Player gets half of the bet back
+        }
+    } else if player.is_bust {
+        (Outcome::Lose, Money::ZERO) //Player bust
+    } else if player.is_blackjack {
+        if dealer.is_blackjack {
+            (Outcome::Push, hand.bet) //Dealer & Player blacjack
+        } else {
+            (Outcome::Blackjack, hand.bet + rules.blackjack_payout.winnings(hand.bet, rules.payout_rounding)) //Player blacjack, paid at the table's blackjack payout
+        }
+    } else if rules.dealer_22_pushes && dealer.best_total == 22 {
+        (Outcome::Push, hand.bet) //Dealer busting with 22 pushes every hand except blackjack
+    } else if dealer.is_bust {
+        (Outcome::Win, hand.bet * 2) //Dealer bust
+    } else if dealer.is_blackjack {
+        (Outcome::Lose, Money::ZERO) //Dealer blackjack
+    } else if player.best_total == dealer.best_total {
+        (Outcome::Push, hand.bet) //Dealer and Player have same hand value
+    } else if player.best_total > dealer.best_total {
+        (Outcome::Win, hand.bet * 2) //Player hand is better than Dealer's
+    } else {
+        (Outcome::Lose, Money::ZERO) //Player hand is worse than Dealer's
+    }
+}
+
+fn condition_holds(condition: &Condition, cards: &[PlayingCard], dealer_hand: &[PlayingCard]) -> bool {
+    match condition {
+        Condition::Suited => cards.iter().all(|card| card.suit == cards[0].suit),
+        Condition::SameColour => cards.iter().all(|card| card.suit.colour() == cards[0].suit.colour()),
+        Condition::SameRank => cards.iter().all(|card| card.value == cards[0].value),
+        Condition::Straight => {
+            // An ace counts both low (A-2-3) and high (Q-K-A)
+            let ace_low: Vec<u8> = cards.iter().map(|card| card.value).collect();
+            let ace_high: Vec<u8> = ace_low.iter().map(|value| if *value == 1 { 14 } else { *value }).collect();
+            is_run(ace_low) || is_run(ace_high)
+        },
+        Condition::Total(total) => HandEval::of(cards).best_total == *total,
+        Condition::Bust => HandEval::of(cards).is_bust,
+        Condition::Card(value, suit) => cards.iter().all(|card| card.value == *value && card.suit == *suit),
+        Condition::MinCards(count) => cards.len() >= *count,
+        Condition::MaxCards(count) => cards.len() <= *count,
+        Condition::DealerBlackjack => HandEval::of(dealer_hand).is_blackjack,
+    }
+}
+
+// Whether the values follow each other without gaps once sorted
+fn is_run(mut values: Vec<u8>) -> bool {
+    values.sort();
+    values.windows(2).all(|pair| pair[1] == pair[0] + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BET: Money = Money::from_units(10);
+    const BANKROLL: Money = Money::from_units(100);
+    const SIDE_BET: Money = Money::from_units(5);
+
+    // A round played on a shoe stacked to deal the given card values in order
+    struct Table {
+        rules: TableRules,
+        shoe: Shoe,
+        rng: ShoeRng,
+        bankrolls: Vec<Money>,
+        round: Round,
+        events: Vec<Event>,
+    }
+
+    impl Table {
+        fn new(rules: TableRules, values: &[u8]) -> Table {
+            let bets = vec![bet(BET, &rules)];
+            Table::seated(rules, bets, values)
+        }
+
+        fn seated(rules: TableRules, bets: Vec<SeatBet>, values: &[u8]) -> Table {
+            let cards: Vec<PlayingCard> = values.iter().map(|value| card(*value, CardSuit::Spade)).collect();
+            Table::dealt(rules, bets, &cards)
+        }
+
+        fn dealt(rules: TableRules, bets: Vec<SeatBet>, cards: &[PlayingCard]) -> Table {
+            let mut shoe = stacked(cards);
+            let mut rng = seeded_rng(1);
+            let mut bankrolls = vec![BANKROLL; bets.len()];
+            let (round, events) = Round::start(bets, &mut bankrolls, &mut shoe, &mut rng, &rules).unwrap();
+            Table { rules, shoe, rng, bankrolls, round, events }
+        }
+
+        fn act(&mut self, action: Action) -> Result<(), ActionError> {
+            let events = self.round.act(action, &mut self.bankrolls, &mut self.shoe, &mut self.rng, &self.rules)?;
+            self.events.extend(events);
+            Ok(())
+        }
+
+        fn settlements(&self) -> &[Settlement] {
+            match self.events.last() {
+                Some(Event::RoundOver(settlements)) => settlements,
+                last => panic!("the round isn't over, the last event is {:?}", last),
+            }
+        }
+
+        fn happened(&self, event: &Event) -> bool {
+            self.events.contains(event)
+        }
+    }
+
+    fn stacked(cards: &[PlayingCard]) -> Shoe {
+        Shoe { cards: cards.iter().rev().copied().collect(), discards: Vec::new(), cut_card: 0 }
+    }
+
+    fn card(value: u8, suit: CardSuit) -> PlayingCard {
+        PlayingCard { suit, value }
+    }
+
+    fn bet(amount: Money, rules: &TableRules) -> SeatBet {
+        SeatBet { bet: amount, side_bets: vec![Money::ZERO; rules.side_bets.len()] }
+    }
+
+    // The main bet with SIDE_BET on each of the named side bets of the default table
+    fn side_bets(names: &[&str], rules: &TableRules) -> SeatBet {
+        let mut seat = bet(BET, rules);
+        for name in names {
+            seat.side_bets[side_bet_index(name, rules)] = SIDE_BET;
+        }
+        seat
+    }
+
+    fn side_bet_index(name: &str, rules: &TableRules) -> usize {
+        rules.side_bets.iter().position(|side_bet| side_bet.name == name).unwrap()
+    }
+
+    // The paytable line a side bet paid on and what it returned, None when it hasn't been settled yet
+    fn side_bet_result(table: &Table, name: &str) -> Option<(Option<usize>, Money)> {
+        let index = side_bet_index(name, &table.rules);
+        table.events.iter().find_map(|event| match event {
+            Event::SideBetSettled { side_bet, line, returned, .. } if *side_bet == index => Some((*line, *returned)),
+            _ => None,
+        })
+    }
+
+    // Deals the player the first and third card and the dealer the second and fourth, then the rest in order
+    fn side_bet_table(names: &[&str], cards: &[PlayingCard]) -> Table {
+        let rules = TableRules::default();
+        let seat = side_bets(names, &rules);
+        Table::dealt(rules, vec![seat], cards)
+    }
+
+    fn settlement(outcomes: Vec<Outcome>, wagered: Money, returned: Money) -> Settlement {
+        Settlement { outcomes, wagered, returned }
+    }
+
+    #[test]
+    fn double_takes_one_card_for_twice_the_bet() {
+        // 5 and 6 against a 10 with 7 in the hole, the double draws a 10
+        let mut table = Table::new(TableRules::default(), &[5, 10, 6, 7, 10]);
+        assert!(table.happened(&Event::DealerChecks));
+        assert_eq!(table.round.phase, Phase::PlayerTurn);
+        table.act(Action::Double).unwrap();
+        assert_eq!(table.settlements(), [settlement(vec![Outcome::Win], BET * 2, BET * 4)]);
+        assert_eq!(table.bankrolls, [BANKROLL + BET * 2]);
+    }
+
+    #[test]
+    fn double_only_on_the_first_two_cards() {
+        let mut table = Table::new(TableRules::default(), &[5, 10, 6, 7, 2]);
+        table.act(Action::Hit).unwrap();
+        assert_eq!(table.act(Action::Double), Err(ActionError::NotAllowed));
+    }
+
+    #[test]
+    fn split_and_resplit() {
+        // Eights against a 6, the first split hand draws another 8 and is split again
+        let mut table = Table::new(TableRules::default(), &[8, 6, 8, 10, 8, 3, 10, 9, 10]);
+        table.act(Action::Split).unwrap();
+        table.act(Action::Split).unwrap();
+        assert_eq!(table.round.current_hand().cards.len(), 2);
+        for _ in 0..3 {
+            table.act(Action::Stand).unwrap();
+        }
+        assert_eq!(table.events.iter().filter(|event| matches!(event, Event::HandSplit { .. })).count(), 2);
+        assert!(table.happened(&Event::DealerBust));
+        assert_eq!(table.settlements(), [settlement(vec![Outcome::Win; 3], BET * 3, BET * 6)]);
+    }
+
+    #[test]
+    fn split_stops_at_the_hand_limit() {
+        let rules = TableRules { max_split_hands: 2, ..TableRules::default() };
+        let mut table = Table::new(rules, &[8, 6, 8, 10, 8]);
+        table.act(Action::Split).unwrap();
+        assert_eq!(table.act(Action::Split), Err(ActionError::NotAllowed));
+    }
+
+    #[test]
+    fn split_aces_only_split_again_or_stand() {
+        let rules = TableRules { resplit_aces: true, split_aces_one_card: true, ..TableRules::default() };
+        let mut table = Table::new(rules, &[1, 9, 1, 7, 1, 5, 9, 10, 2]);
+        table.act(Action::Split).unwrap();
+        assert!(!table.round.can_hit(&table.rules));
+        assert_eq!(table.act(Action::Hit), Err(ActionError::NotAllowed));
+        assert_eq!(table.act(Action::Double), Err(ActionError::NotAllowed));
+        table.act(Action::Split).unwrap();
+        // Every ace got one card, the split 21 isn't a blackjack
+        assert_eq!(table.round.seats[0].hands.iter().map(|hand| hand.cards.len()).collect::<Vec<_>>(), [2, 2, 2]);
+        assert_eq!(table.settlements()[0].outcomes, [Outcome::Lose, Outcome::Win, Outcome::Win]);
+    }
+
+    #[test]
+    fn early_surrender_beats_a_dealer_blackjack() {
+        let rules = TableRules { surrender: SurrenderRule::Early, ..TableRules::default() };
+        let mut table = Table::new(rules, &[10, 10, 6, 1]);
+        assert_eq!(table.round.phase, Phase::EarlySurrender);
+        table.act(Action::Surrender).unwrap();
+        assert_eq!(table.settlements(), [settlement(vec![Outcome::Surrendered], BET, Money::from_units(5))]);
+    }
+
+    #[test]
+    fn late_surrender_returns_half_the_bet() {
+        let mut table = Table::new(TableRules::default(), &[10, 9, 6, 7]);
+        table.act(Action::Surrender).unwrap();
+        assert!(!table.happened(&Event::DealerHits));
+        assert_eq!(table.settlements(), [settlement(vec![Outcome::Surrendered], BET, Money::from_units(5))]);
+    }
+
+    #[test]
+    fn late_surrender_loses_to_a_blackjack_without_a_hole_card() {
+        let rules = TableRules { hole_card: HoleCardRule::NoHoleCard, ..TableRules::default() };
+        let mut table = Table::new(rules, &[10, 10, 6, 1]);
+        assert_eq!(table.round.dealer_hand.len(), 1);
+        table.act(Action::Surrender).unwrap();
+        assert!(table.happened(&Event::DealerBlackjack));
+        assert_eq!(table.settlements(), [settlement(vec![Outcome::Lose], BET, Money::ZERO)]);
+    }
+
+    #[test]
+    fn peek_with_an_ace_pays_insurance() {
+        let mut table = Table::new(TableRules::default(), &[10, 1, 9, 10]);
+        assert_eq!(table.round.phase, Phase::Insurance);
+        table.act(Action::Insurance(Money::from_units(5))).unwrap();
+        assert!(table.happened(&Event::DealerBlackjack));
+        assert!(table.happened(&Event::InsuranceSettled { seat: 0, returned: Money::from_units(15) }));
+        assert_eq!(table.settlements(), [settlement(vec![Outcome::Lose], Money::from_units(15), Money::from_units(15))]);
+        assert_eq!(table.bankrolls, [BANKROLL]);
+    }
+
+    #[test]
+    fn peek_with_an_ace_loses_insurance_and_plays_on() {
+        let mut table = Table::new(TableRules::default(), &[10, 1, 9, 7]);
+        assert_eq!(table.act(Action::Insurance(Money::from_units(6))), Err(ActionError::NotAllowed)); // more than half the bet
+        table.act(Action::Insurance(Money::from_units(5))).unwrap();
+        assert!(table.happened(&Event::DealerChecks));
+        assert_eq!(table.round.phase, Phase::PlayerTurn);
+        table.act(Action::Stand).unwrap();
+        assert!(table.happened(&Event::InsuranceSettled { seat: 0, returned: Money::ZERO }));
+        assert_eq!(table.settlements(), [settlement(vec![Outcome::Win], Money::from_units(15), BET * 2)]);
+    }
+
+    #[test]
+    fn peek_with_a_ten() {
+        // No insurance on a ten, the dealer's blackjack ends the round straight away
+        let table = Table::new(TableRules::default(), &[10, 10, 9, 1]);
+        assert!(table.happened(&Event::DealerBlackjack));
+        assert_eq!(table.settlements(), [settlement(vec![Outcome::Lose], BET, Money::ZERO)]);
+
+        let table = Table::new(TableRules::default(), &[10, 10, 9, 7]);
+        assert!(table.happened(&Event::DealerChecks));
+        assert_eq!(table.round.phase, Phase::PlayerTurn);
+    }
+
+    #[test]
+    fn even_money_pays_a_blackjack_one_to_one() {
+        let mut table = Table::new(TableRules::default(), &[1, 1, 10, 7]);
+        assert_eq!(table.round.phase, Phase::EvenMoney);
+        table.act(Action::EvenMoney).unwrap();
+        assert_eq!(table.settlements(), [settlement(vec![Outcome::EvenMoney], BET, BET * 2)]);
+    }
+
+    #[test]
+    fn declined_even_money_pushes_on_a_dealer_blackjack() {
+        let mut table = Table::new(TableRules::default(), &[1, 1, 10, 10]);
+        table.act(Action::Decline).unwrap();
+        assert_eq!(table.settlements(), [settlement(vec![Outcome::Push], BET, BET)]);
+    }
+
+    #[test]
+    fn blackjack_pays_three_to_two() {
+        let table = Table::new(TableRules::default(), &[1, 9, 10, 7]);
+        assert_eq!(table.settlements(), [settlement(vec![Outcome::Blackjack], BET, Money::from_units(25))]);
+    }
+
+    #[test]
+    fn dealer_22_pushes() {
+        let rules = TableRules { dealer_22_pushes: true, ..TableRules::default() };
+        let mut table = Table::new(rules, &[10, 6, 8, 6, 10]);
+        table.act(Action::Stand).unwrap();
+        assert!(table.happened(&Event::DealerBust));
+        assert_eq!(table.settlements(), [settlement(vec![Outcome::Push], BET, BET)]);
+    }
+
+    #[test]
+    fn round_over_settles_every_seat() {
+        // The middle seat sits out, the others play 19 and 16 against the dealer's 18
+        let rules = TableRules::default();
+        let bets = vec![bet(BET, &rules), bet(Money::ZERO, &rules), bet(BET, &rules)];
+        let mut table = Table::seated(rules, bets, &[10, 10, 10, 9, 6, 8, 10]);
+        assert_eq!(table.round.active_seat, 0);
+        table.act(Action::Stand).unwrap();
+        assert_eq!(table.round.active_seat, 2);
+        table.act(Action::Hit).unwrap();
+        assert!(table.happened(&Event::PlayerBust { seat: 2, hand: 0 }));
+        assert_eq!(table.settlements(), [
+            settlement(vec![Outcome::Win], BET, BET * 2),
+            settlement(Vec::new(), Money::ZERO, Money::ZERO),
+            settlement(vec![Outcome::Lose], BET, Money::ZERO),
+        ]);
+        assert_eq!(table.bankrolls, [BANKROLL + BET, BANKROLL, BANKROLL - BET]);
+    }
+
+    #[test]
+    fn dealer_plays_out_for_a_bet_on_the_dealer_hand() {
+        // The player busts, the dealer still draws to bust with three cards for the Buster bet
+        let rules = TableRules::default();
+        let buster = rules.side_bets.iter().position(|side_bet| side_bet.cards == SideBetCards::DealerHand).unwrap();
+        let mut seat = bet(BET, &rules);
+        seat.side_bets[buster] = Money::from_units(5);
+        let mut table = Table::seated(rules, vec![seat], &[10, 6, 6, 10, 10, 10]);
+        table.act(Action::Hit).unwrap();
+        assert!(table.happened(&Event::DealerBust));
+        assert!(table.happened(&Event::SideBetSettled { seat: 0, side_bet: buster, line: Some(4), returned: Money::from_units(15) }));
+    }
+
+    #[test]
+    fn dealer_plays_out_for_a_bet_on_the_dealer_hand_after_early_surrender() {
+        let rules = TableRules { surrender: SurrenderRule::Early, ..TableRules::default() };
+        let buster = rules.side_bets.iter().position(|side_bet| side_bet.cards == SideBetCards::DealerHand).unwrap();
+        let mut seat = bet(BET, &rules);
+        seat.side_bets[buster] = Money::from_units(5);
+        let mut table = Table::seated(rules, vec![seat], &[10, 10, 6, 6, 6]);
+        assert_eq!(table.round.phase, Phase::EarlySurrender);
+        table.act(Action::Surrender).unwrap();
+        assert!(table.happened(&Event::DealerBust));
+        assert!(table.happened(&Event::SideBetSettled { seat: 0, side_bet: buster, line: Some(4), returned: Money::from_units(15) }));
+        assert_eq!(table.settlements()[0].outcomes, [Outcome::Surrendered]);
+    }
+
+    #[test]
+    fn straight_counts_the_ace_low_and_high() {
+        use CardSuit::*;
+        // 21+3 lines: Suited Trips, Straight Flush, Three of a Kind, Straight (10 to 1), Flush
+        let ace_low = side_bet_table(&["21+3"], &[card(1, Spade), card(3, Heart), card(2, Diamond), card(9, Club)]);
+        assert_eq!(side_bet_result(&ace_low, "21+3"), Some((Some(3), SIDE_BET * 11)));
+        let ace_high = side_bet_table(&["21+3"], &[card(12, Spade), card(1, Heart), card(13, Diamond), card(9, Club)]);
+        assert_eq!(side_bet_result(&ace_high, "21+3"), Some((Some(3), SIDE_BET * 11)));
+        let round_the_corner = side_bet_table(&["21+3"], &[card(13, Spade), card(2, Heart), card(1, Diamond), card(9, Club)]);
+        assert_eq!(side_bet_result(&round_the_corner, "21+3"), Some((None, Money::ZERO)));
+    }
+
+    #[test]
+    fn same_colour_pairs_mix_suits_of_one_colour() {
+        use CardSuit::*;
+        // Pairs lines: Perfect Pair, Coloured Pair (12 to 1), Mixed Pair (6 to 1)
+        let coloured = side_bet_table(&["Pairs"], &[card(8, Heart), card(10, Club), card(8, Diamond), card(7, Club)]);
+        assert_eq!(side_bet_result(&coloured, "Pairs"), Some((Some(1), SIDE_BET * 13)));
+        let mixed = side_bet_table(&["Pairs"], &[card(8, Heart), card(10, Club), card(8, Spade), card(7, Club)]);
+        assert_eq!(side_bet_result(&mixed, "Pairs"), Some((Some(2), SIDE_BET * 7)));
+    }
+
+    #[test]
+    fn queen_of_hearts_pair_pays_ladies() {
+        use CardSuit::*;
+        // Ladies lines: with a dealer blackjack, Queen of Hearts Pair (125 to 1), then the 20s
+        let mut table = side_bet_table(&["Ladies"], &[card(12, Heart), card(10, Club), card(12, Heart), card(7, Club)]);
+        table.act(Action::Stand).unwrap();
+        assert_eq!(side_bet_result(&table, "Ladies"), Some((Some(1), SIDE_BET * 126)));
+        let mut suited = side_bet_table(&["Ladies"], &[card(12, Heart), card(10, Club), card(13, Heart), card(7, Club)]);
+        suited.act(Action::Stand).unwrap();
+        assert_eq!(side_bet_result(&suited, "Ladies"), Some((Some(3), SIDE_BET * 10)));
+    }
+
+    #[test]
+    fn queen_of_hearts_pair_with_a_dealer_blackjack_pays_the_top_line() {
+        use CardSuit::*;
+        let table = side_bet_table(&["Ladies"], &[card(12, Heart), card(13, Club), card(12, Heart), card(1, Club)]);
+        assert!(table.happened(&Event::DealerBlackjack));
+        assert_eq!(side_bet_result(&table, "Ladies"), Some((Some(0), SIDE_BET * 1001)));
+        assert_eq!(table.settlements()[0].outcomes, [Outcome::Lose]);
+    }
+
+    #[test]
+    fn buster_pays_by_the_number_of_dealer_cards() {
+        use CardSuit::*;
+        // The player stands on 20, the dealer draws 2, 3, 4 and 6 to 15 and busts with a 10 as the fifth card
+        let dealer = [card(3, Club), card(4, Club), card(6, Club), card(10, Club)];
+        let mut cards = vec![card(10, Spade), card(2, Club), card(10, Heart)];
+        cards.extend(dealer);
+        let mut table = side_bet_table(&["Buster"], &cards);
+        table.act(Action::Stand).unwrap();
+        assert!(table.happened(&Event::DealerBust));
+        assert_eq!(table.round.dealer_hand.len(), 5);
+        // Buster lines: 8+ cards, 7, 6, 5 cards (4 to 1), then 3 or 4 cards
+        assert_eq!(side_bet_result(&table, "Buster"), Some((Some(3), SIDE_BET * 5)));
+    }
+
+    #[test]
+    fn side_bets_on_the_first_cards_settle_after_the_deal_and_the_rest_at_the_end() {
+        use CardSuit::*;
+        let mut table = side_bet_table(&["21+3", "Ladies"], &[card(10, Heart), card(9, Club), card(10, Diamond), card(8, Club)]);
+        assert_eq!(table.round.phase, Phase::PlayerTurn);
+        assert_eq!(side_bet_result(&table, "21+3"), Some((None, Money::ZERO)));
+        assert_eq!(side_bet_result(&table, "Ladies"), None);
+        assert_eq!(table.bankrolls, [BANKROLL - BET - SIDE_BET * 2]);
+        table.act(Action::Stand).unwrap();
+        // Any 20 pays 4 to 1
+        assert_eq!(side_bet_result(&table, "Ladies"), Some((Some(4), SIDE_BET * 5)));
+        assert_eq!(table.bankrolls, [BANKROLL + BET + SIDE_BET * 3]);
+    }
+
+    #[test]
+    fn side_bets_above_the_table_limit_are_refused() {
+        let rules = TableRules::default();
+        let mut seat = bet(BET, &rules);
+        seat.side_bets[0] = rules.max_side_bet + Money::CENT;
+        let mut bankrolls = [BANKROLL];
+        let result = Round::start(vec![seat.clone()], &mut bankrolls, &mut stacked(&[]), &mut seeded_rng(1), &rules);
+        assert_eq!(result.err(), Some(ActionError::NotAllowed));
+        assert_eq!(bankrolls, [BANKROLL]);
+        seat.side_bets[0] = rules.max_side_bet;
+        let values = [card(10, CardSuit::Spade); 4];
+        assert!(Round::start(vec![seat], &mut bankrolls, &mut stacked(&values), &mut seeded_rng(1), &rules).is_ok());
+    }
+
+    fn shown(values: &[u8]) -> String {
+        let cards: Vec<PlayingCard> = values.iter().map(|value| card(*value, CardSuit::Club)).collect();
+        HandEval::new(&cards, false).to_string()
+    }
+
+    #[test]
+    fn soft_totals_show_both_counts() {
+        assert_eq!(shown(&[1, 6]), "7/17");
+        assert_eq!(shown(&[1, 2, 3]), "6/16");
+        assert_eq!(shown(&[1, 1]), "2/12");
+    }
+
+    #[test]
+    fn totals_show_one_count_once_the_ace_must_count_low() {
+        assert_eq!(shown(&[1, 6, 10]), "17");
+        assert_eq!(shown(&[1, 5, 5, 10]), "21");
+        assert_eq!(shown(&[10, 6]), "16");
+        assert_eq!(shown(&[10, 6, 9]), "25");
+    }
+
+    #[test]
+    fn twenty_one_shows_one_count() {
+        assert_eq!(shown(&[1, 13]), "21");
+        assert!(HandEval::new(&[card(1, CardSuit::Club), card(13, CardSuit::Club)], false).is_blackjack);
+        assert_eq!(shown(&[1, 5, 5]), "21");
+    }
+}
@@ -0,0 +1,237 @@
+use std::{fmt, iter, ops};
+use serde::{Deserialize, Serialize};
+
+// Amounts of money are counted in whole cents, so bets, bankrolls and payouts add up exactly. The rules file still
+// writes them as currency, 2.5 is two and a half.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "f64", into = "f64")]
+pub struct Money(i64);
+
+// Payouts that don't come out in whole cents, like 3:2 on an odd bet or half of it back on a surrender, are rounded
+// the way the table says
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Down, // the odd cent stays with the house
+    Nearest, // half a cent and more goes to the player
+    Up, // the odd cent goes to the player
+}
+
+pub const CENTS_PER_UNIT: i64 = 100;
+pub const CURRENCY: &str = "$";
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+    pub const CENT: Money = Money(1);
+
+    pub const fn from_cents(cents: i64) -> Money {
+        Money(cents)
+    }
+
+    pub const fn from_units(units: i64) -> Money {
+        Money(units * CENTS_PER_UNIT)
+    }
+
+    pub fn cents(self) -> i64 {
+        self.0
+    }
+
+    // Only meant for statistics and display, every amount that's paid or wagered stays in cents
+    pub fn as_f64(self) -> f64 {
+        self.0 as f64 / CENTS_PER_UNIT as f64
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        self.0.checked_add(other.0).map(Money)
+    }
+
+    pub fn checked_sub(self, other: Money) -> Option<Money> {
+        self.0.checked_sub(other.0).map(Money)
+    }
+
+    pub fn checked_mul(self, times: i64) -> Option<Money> {
+        self.0.checked_mul(times).map(Money)
+    }
+
+    // The amount times numerator / denominator, with a fraction of a cent rounded as asked
+    pub fn checked_mul_ratio(self, numerator: u32, denominator: u32, rounding: Rounding) -> Option<Money> {
+        if denominator == 0 {
+            return None;
+        }
+        let scaled = self.0 as i128 * numerator as i128;
+        let denominator = denominator as i128;
+        let cents = match rounding {
+            Rounding::Down => scaled.div_euclid(denominator),
+            Rounding::Up => -(-scaled).div_euclid(denominator),
+            Rounding::Nearest => (scaled * 2 + denominator).div_euclid(denominator * 2),
+        };
+        i64::try_from(cents).ok().map(Money)
+    }
+
+    pub fn mul_ratio(self, numerator: u32, denominator: u32, rounding: Rounding) -> Money {
+        self.checked_mul_ratio(numerator, denominator, rounding).expect("money out of range")
+    }
+
+    // How many whole steps of the given size fit in the amount
+    pub fn steps(self, step: Money) -> i64 {
+        if step.0 <= 0 {
+            return 0;
+        }
+        self.0.div_euclid(step.0)
+    }
+}
+
+// The rules file and saved profiles write amounts in currency units, anything finer than a cent is rounded to the nearest cent
+impl TryFrom<f64> for Money {
+    type Error = String;
+
+    fn try_from(value: f64) -> Result<Money, String> {
+        let cents = (value * CENTS_PER_UNIT as f64).round();
+        if !cents.is_finite() || cents.abs() > i64::MAX as f64 / 2.0 {
+            return Err(format!("{} is not an amount of money", value));
+        }
+        Ok(Money(cents as i64))
+    }
+}
+
+impl From<Money> for f64 {
+    fn from(money: Money) -> f64 {
+        money.as_f64()
+    }
+}
+
+// The operators panic instead of wrapping around when an amount goes out of range, the checked_ methods return None
+impl ops::Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        self.checked_add(other).expect("money out of range")
+    }
+}
+
+impl ops::Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        self.checked_sub(other).expect("money out of range")
+    }
+}
+
+impl ops::Mul<i64> for Money {
+    type Output = Money;
+
+    fn mul(self, times: i64) -> Money {
+        self.checked_mul(times).expect("money out of range")
+    }
+}
+
+impl ops::Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(self.0.checked_neg().expect("money out of range"))
+    }
+}
+
+impl ops::AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        *self = *self + other;
+    }
+}
+
+impl ops::SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        *self = *self - other;
+    }
+}
+
+impl iter::Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(amounts: I) -> Money {
+        amounts.fold(Money::ZERO, |total, amount| total + amount)
+    }
+}
+
+impl<'a> iter::Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(amounts: I) -> Money {
+        amounts.copied().sum()
+    }
+}
+
+// Shown as currency, like $12.50 or -$0.50. {:+} also signs gains, like +$1.50.
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 {
+            "-"
+        } else if f.sign_plus() {
+            "+"
+        } else {
+            ""
+        };
+        let cents = self.0.unsigned_abs();
+        let units = CENTS_PER_UNIT as u64;
+        write!(f, "{}{}{}.{:02}", sign, CURRENCY, cents / units, cents % units)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cents(cents: i64) -> Money {
+        Money::from_cents(cents)
+    }
+
+    #[test]
+    fn three_to_two_on_an_odd_cent() {
+        // 3:2 on $10.01 is $15.015
+        let bet = cents(1001);
+        assert_eq!(bet.mul_ratio(3, 2, Rounding::Down), cents(1501));
+        assert_eq!(bet.mul_ratio(3, 2, Rounding::Nearest), cents(1502));
+        assert_eq!(bet.mul_ratio(3, 2, Rounding::Up), cents(1502));
+    }
+
+    #[test]
+    fn six_to_five_on_odd_cents() {
+        // 6:5 on $10.01 is $12.012 and on $10.03 $12.036
+        assert_eq!(cents(1001).mul_ratio(6, 5, Rounding::Down), cents(1201));
+        assert_eq!(cents(1001).mul_ratio(6, 5, Rounding::Nearest), cents(1201));
+        assert_eq!(cents(1001).mul_ratio(6, 5, Rounding::Up), cents(1202));
+        assert_eq!(cents(1003).mul_ratio(6, 5, Rounding::Down), cents(1203));
+        assert_eq!(cents(1003).mul_ratio(6, 5, Rounding::Nearest), cents(1204));
+        assert_eq!(cents(1003).mul_ratio(6, 5, Rounding::Up), cents(1204));
+    }
+
+    #[test]
+    fn whole_cents_are_never_rounded() {
+        for rounding in [Rounding::Down, Rounding::Nearest, Rounding::Up] {
+            assert_eq!(cents(1000).mul_ratio(3, 2, rounding), cents(1500));
+            assert_eq!(cents(1000).mul_ratio(6, 5, rounding), cents(1200));
+        }
+    }
+
+    #[test]
+    fn half_a_bet_back_on_surrender() {
+        // Half of $0.05 is two and a half cents
+        assert_eq!(cents(5).mul_ratio(1, 2, Rounding::Down), cents(2));
+        assert_eq!(cents(5).mul_ratio(1, 2, Rounding::Nearest), cents(3));
+        assert_eq!(cents(5).mul_ratio(1, 2, Rounding::Up), cents(3));
+        assert_eq!(cents(1000).mul_ratio(1, 2, Rounding::Down), cents(500));
+    }
+
+    #[test]
+    fn overflow_returns_none() {
+        let most = Money::from_cents(i64::MAX);
+        assert_eq!(most.checked_mul_ratio(3, 2, Rounding::Down), None);
+        assert_eq!(most.checked_mul_ratio(1, 2, Rounding::Down), Some(cents(i64::MAX / 2)));
+        assert_eq!(cents(100).checked_mul_ratio(1, 0, Rounding::Down), None);
+        assert_eq!(most.checked_add(Money::CENT), None);
+        assert_eq!(most.checked_mul(2), None);
+    }
+}
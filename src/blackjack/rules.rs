@@ -0,0 +1,512 @@
+use std::{fmt, fs, io, path::Path};
+use serde::Deserialize;
+use super::engine::CardSuit;
+use super::money::{Money, Rounding};
+use super::ai::Personality;
+
+pub const RULES_FILE: &str = "table_rules.ron";
+pub const MAX_SEATS: usize = 7;
+
+// Table limits and house rules, read from assets/table_rules.ron at startup. Fields missing from the file keep their default value.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TableRules {
+    pub starting_money: Money,
+    pub bet_step: Money,
+    pub min_bet: Money,
+    pub max_bet: Money,
+    pub decks: usize,
+    pub penetration: f32,
+    pub seed: Option<u64>, // the --seed command line argument takes precedence
+    pub hole_card: HoleCardRule,
+    pub dealer_hits_soft_17: bool,
+    pub blackjack_payout: Payout,
+    pub payout_rounding: Rounding, // for payouts that don't come out in whole cents
+    pub dealer_22_pushes: bool,
+    pub double_rule: DoubleRule,
+    pub double_after_split: bool,
+    pub max_split_hands: usize,
+    pub resplit_aces: bool,
+    pub split_aces_one_card: bool,
+    pub insurance: bool,
+    pub surrender: SurrenderRule,
+    pub max_side_bet: Money,
+    pub side_bets: Vec<SideBet>,
+    pub seats: Vec<SeatKind>, // in turn order, from 1 to MAX_SEATS
+    pub game_over: GameOverRules,
+}
+
+// What the table offers a player whose bankroll can't cover the minimum bet. A loan is paid back from a share of the
+// winnings of every round after it.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameOverRules {
+    pub bailout: Money, // given to the player, zero turns bailouts off
+    pub loan: Money, // lent to the player, zero turns loans off
+    pub interest_percent: u32, // added to the loan when it's taken
+    pub repay_percent: u32, // of every round's winnings goes to the debt until it's paid off
+}
+
+// Payout ratio for a winning blackjack, paid on top of returning the bet. Written as "3:2" in the rules file.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Payout {
+    pub numerator: u32,
+    pub denominator: u32,
+}
+
+// A side bet is settled by walking down its paytable, the first line whose conditions all hold for the bet's cards pays
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SideBet {
+    pub name: String,
+    pub cards: SideBetCards,
+    pub settle: SideBetSettle,
+    pub paytable: Vec<PaytableLine>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PaytableLine {
+    pub name: String,
+    pub pays: u32, // to 1
+    pub when: Vec<Condition>,
+}
+
+// The cards a side bet looks at
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SideBetCards {
+    FirstTwo,
+    FirstTwoAndUpcard,
+    DealerHand,
+}
+
+// Bets on the first cards are settled before the player's turn, bets on the dealer's hand once the dealer has played
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SideBetSettle {
+    AfterDeal,
+    GameEnd,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Suited,
+    SameColour,
+    SameRank,
+    Straight,
+    Total(u8),
+    Bust,
+    Card(u8, CardSuit), // every card is this exact card
+    MinCards(usize),
+    MaxCards(usize),
+    DealerBlackjack,
+}
+
+// Which two card totals the player is allowed to double down on
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoubleRule {
+    AnyTwoCards,
+    NineToEleven,
+    TenToEleven,
+}
+
+// US tables deal the dealer a hole card and peek for blackjack under an ace or ten, European tables deal the second card at DealerTurn
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoleCardRule {
+    Peek,
+    NoHoleCard,
+}
+
+// Early surrender is allowed even when the dealer turns out to have blackjack, late surrender only after the dealer checked for it
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurrenderRule {
+    NoSurrender,
+    Late,
+    Early,
+}
+
+// Who sits at a seat, a human takes the keyboard on their turn
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeatKind {
+    Human,
+    Computer(Personality),
+}
+
+#[derive(Debug)]
+pub enum RulesError {
+    Read(io::Error),
+    Parse(ron::error::SpannedError),
+    Invalid(Vec<String>),
+}
+
+impl Default for TableRules {
+    fn default() -> TableRules {
+        TableRules {
+            starting_money: Money::from_units(5),
+            bet_step: Money::from_units(1),
+            min_bet: Money::from_units(1),
+            max_bet: Money::from_units(10),
+            decks: 6,
+            penetration: 0.75,
+            seed: None,
+            hole_card: HoleCardRule::Peek,
+            dealer_hits_soft_17: false,
+            blackjack_payout: Payout::THREE_TO_TWO,
+            payout_rounding: Rounding::Down,
+            dealer_22_pushes: false,
+            double_rule: DoubleRule::AnyTwoCards,
+            double_after_split: true,
+            max_split_hands: 4,
+            resplit_aces: false,
+            split_aces_one_card: true,
+            insurance: true,
+            surrender: SurrenderRule::Late,
+            max_side_bet: Money::from_units(5),
+            side_bets: default_side_bets(),
+            seats: vec![SeatKind::Human],
+            game_over: GameOverRules::default(),
+        }
+    }
+}
+
+impl Default for GameOverRules {
+    fn default() -> GameOverRules {
+        GameOverRules {
+            bailout: Money::from_units(5),
+            loan: Money::from_units(20),
+            interest_percent: 10,
+            repay_percent: 50,
+        }
+    }
+}
+
+impl GameOverRules {
+    // The loan with its interest, the odd cent of interest goes to the house
+    pub fn loan_debt(&self) -> Money {
+        self.loan + self.loan.mul_ratio(self.interest_percent, 100, Rounding::Up)
+    }
+
+    // The part of a round's net result that pays off the debt, nothing after a losing round
+    pub fn repayment(&self, net: Money, debt: Money) -> Money {
+        if !net.is_positive() {
+            return Money::ZERO;
+        }
+        net.mul_ratio(self.repay_percent, 100, Rounding::Up).min(debt)
+    }
+}
+
+impl TableRules {
+    // Insurance is raised and lowered in half bet steps
+    pub fn insurance_step(&self) -> Money {
+        self.bet_step.mul_ratio(1, 2, Rounding::Down).max(Money::CENT)
+    }
+
+    // Insurance is a side wager of up to half the bet, an odd cent is never insured
+    pub fn max_insurance(&self, bet: Money) -> Money {
+        bet.mul_ratio(1, 2, Rounding::Down)
+    }
+
+    pub fn load(path: &Path) -> Result<TableRules, RulesError> {
+        let file = fs::read_to_string(path).map_err(RulesError::Read)?;
+        let rules: TableRules = ron::from_str(&file).map_err(RulesError::Parse)?;
+        let problems = rules.validate();
+        if problems.is_empty() {
+            Ok(rules)
+        } else {
+            Err(RulesError::Invalid(problems))
+        }
+    }
+
+    // Returns a description of every setting that can't be played with
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.bet_step.is_positive() {
+            problems.push(format!("bet_step must be positive, got {}", self.bet_step));
+        }
+        if !self.min_bet.is_positive() {
+            problems.push(format!("min_bet must be positive, got {}", self.min_bet));
+        }
+        if self.max_bet < self.min_bet {
+            problems.push(format!("max_bet ({}) is lower than min_bet ({})", self.max_bet, self.min_bet));
+        }
+        if self.starting_money.is_negative() {
+            problems.push(format!("starting_money can't be negative, got {}", self.starting_money));
+        }
+        if ![1, 2, 6, 8].contains(&self.decks) {
+            problems.push(format!("decks must be 1, 2, 6 or 8, got {}", self.decks));
+        }
+        if !(self.penetration > 0.0 && self.penetration <= 1.0) {
+            problems.push(format!("penetration must be above 0.0 and at most 1.0, got {}", self.penetration));
+        }
+        if self.max_side_bet.is_negative() {
+            problems.push(format!("max_side_bet can't be negative, got {}", self.max_side_bet));
+        }
+        if self.max_split_hands < 1 {
+            problems.push(format!("max_split_hands must be at least 1, got {}", self.max_split_hands));
+        }
+        if self.game_over.bailout.is_negative() || self.game_over.loan.is_negative() {
+            problems.push("game_over bailout and loan can't be negative".to_string());
+        }
+        if self.game_over.loan.is_positive() && !(1..=100).contains(&self.game_over.repay_percent) {
+            problems.push(format!("game_over repay_percent must be from 1 to 100, got {}", self.game_over.repay_percent));
+        }
+        if self.seats.is_empty() || self.seats.len() > MAX_SEATS {
+            problems.push(format!("seats must list 1 to {} seats, got {}", MAX_SEATS, self.seats.len()));
+        }
+        for side_bet in self.side_bets.iter() {
+            if side_bet.paytable.is_empty() {
+                problems.push(format!("side bet {} has an empty paytable", side_bet.name));
+            }
+            for line in side_bet.paytable.iter() {
+                if line.when.is_empty() {
+                    problems.push(format!("{} line {} has no conditions", side_bet.name, line.name));
+                }
+                for condition in line.when.iter() {
+                    if let Condition::Card(value, _) = condition {
+                        if !(1..=13).contains(value) {
+                            problems.push(format!("{} line {} asks for a card value of {}, card values go from 1 (ace) to 13 (king)", side_bet.name, line.name, value));
+                        }
+                    }
+                }
+            }
+        }
+        problems
+    }
+}
+
+fn line(name: &str, pays: u32, when: Vec<Condition>) -> PaytableLine {
+    PaytableLine { name: name.to_string(), pays, when }
+}
+
+// 21+3, Perfect Pairs, Lucky Ladies and Buster Blackjack with their usual paytables
+fn default_side_bets() -> Vec<SideBet> {
+    use Condition::*;
+    vec![
+        SideBet {
+            name: "21+3".to_string(),
+            cards: SideBetCards::FirstTwoAndUpcard,
+            settle: SideBetSettle::AfterDeal,
+            paytable: vec![
+                line("Suited Trips", 100, vec![SameRank, Suited]),
+                line("Straight Flush", 40, vec![Straight, Suited]),
+                line("Three of a Kind", 30, vec![SameRank]),
+                line("Straight", 10, vec![Straight]),
+                line("Flush", 5, vec![Suited]),
+            ],
+        },
+        SideBet {
+            name: "Pairs".to_string(),
+            cards: SideBetCards::FirstTwo,
+            settle: SideBetSettle::AfterDeal,
+            paytable: vec![
+                line("Perfect Pair", 25, vec![SameRank, Suited]),
+                line("Coloured Pair", 12, vec![SameRank, SameColour]),
+                line("Mixed Pair", 6, vec![SameRank]),
+            ],
+        },
+        SideBet {
+            name: "Ladies".to_string(),
+            cards: SideBetCards::FirstTwo,
+            settle: SideBetSettle::GameEnd,
+            paytable: vec![
+                line("Queen of Hearts Pair and Dealer Blackjack", 1000, vec![Card(12, CardSuit::Heart), DealerBlackjack]),
+                line("Queen of Hearts Pair", 125, vec![Card(12, CardSuit::Heart)]),
+                line("Matched 20", 19, vec![Total(20), SameRank, Suited]),
+                line("Suited 20", 9, vec![Total(20), Suited]),
+                line("Any 20", 4, vec![Total(20)]),
+            ],
+        },
+        SideBet {
+            name: "Buster".to_string(),
+            cards: SideBetCards::DealerHand,
+            settle: SideBetSettle::GameEnd,
+            paytable: vec![
+                line("Dealer Busts with 8+ Cards", 250, vec![Bust, MinCards(8)]),
+                line("Dealer Busts with 7 Cards", 50, vec![Bust, MinCards(7)]),
+                line("Dealer Busts with 6 Cards", 15, vec![Bust, MinCards(6)]),
+                line("Dealer Busts with 5 Cards", 4, vec![Bust, MinCards(5)]),
+                line("Dealer Busts with 3 or 4 Cards", 2, vec![Bust]),
+            ],
+        },
+    ]
+}
+
+// Falls back to the default rules, with an explanation, when the rules file can't be used
+pub fn load_table_rules(path: &Path) -> TableRules {
+    match TableRules::load(path) {
+        Ok(rules) => rules,
+        Err(error) => {
+            eprintln!("Invalid table rules in {}: {}", path.display(), error);
+            eprintln!("Playing with the default table rules instead");
+            TableRules::default()
+        }
+    }
+}
+
+impl SideBet {
+    // A bet settled at the end of the round on the dealer's cards needs the dealer's hand played out, even when no
+    // player hand is left for the dealer to beat
+    pub fn needs_dealer_hand(&self) -> bool {
+        self.settle == SideBetSettle::GameEnd
+            && (self.cards == SideBetCards::DealerHand || self.paytable.iter().any(|line| line.when.contains(&Condition::DealerBlackjack)))
+    }
+}
+
+impl Payout {
+    pub const THREE_TO_TWO: Payout = Payout { numerator: 3, denominator: 2 };
+    pub const SIX_TO_FIVE: Payout = Payout { numerator: 6, denominator: 5 };
+    pub const TWO_TO_ONE: Payout = Payout { numerator: 2, denominator: 1 };
+    pub const EVEN_MONEY: Payout = Payout { numerator: 1, denominator: 1 };
+
+    // What a winning blackjack bet pays on top of the bet itself
+    pub fn winnings(&self, bet: Money, rounding: Rounding) -> Money {
+        bet.mul_ratio(self.numerator, self.denominator, rounding)
+    }
+}
+
+impl TryFrom<String> for Payout {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Payout, String> {
+        match value.as_str() {
+            "3:2" => return Ok(Payout::THREE_TO_TWO),
+            "6:5" => return Ok(Payout::SIX_TO_FIVE),
+            "2:1" => return Ok(Payout::TWO_TO_ONE),
+            "1:1" | "even money" => return Ok(Payout::EVEN_MONEY),
+            _ => {},
+        }
+        // any other house rule ratio
+        let invalid = || format!("payout must look like \"3:2\", got \"{}\"", value);
+        let (numerator, denominator) = value.split_once(':').ok_or_else(invalid)?;
+        let numerator: u32 = numerator.trim().parse().map_err(|_| invalid())?;
+        let denominator: u32 = denominator.trim().parse().map_err(|_| invalid())?;
+        if denominator == 0 {
+            return Err(format!("payout \"{}\" divides by zero", value));
+        }
+        if numerator == 0 {
+            return Err(format!("payout \"{}\" doesn't pay anything", value));
+        }
+        Ok(Payout { numerator, denominator })
+    }
+}
+
+impl fmt::Display for Payout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} to {}", self.numerator, self.denominator)
+    }
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RulesError::Read(error) => write!(f, "could not read the file ({})", error),
+            RulesError::Parse(error) => write!(f, "could not parse the file ({})", error),
+            RulesError::Invalid(problems) => write!(f, "{}", problems.join(", ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payout(value: &str) -> Result<Payout, String> {
+        Payout::try_from(value.to_string())
+    }
+
+    #[test]
+    fn payouts_parse_from_ratios() {
+        assert_eq!(payout("3:2"), Ok(Payout::THREE_TO_TWO));
+        assert_eq!(payout("6:5"), Ok(Payout::SIX_TO_FIVE));
+        assert_eq!(payout("even money"), Ok(Payout::EVEN_MONEY));
+        assert_eq!(payout(" 7 : 5 "), Ok(Payout { numerator: 7, denominator: 5 }));
+    }
+
+    #[test]
+    fn payouts_that_pay_nothing_or_divide_by_zero_are_rejected() {
+        assert_eq!(payout("0:1"), Err("payout \"0:1\" doesn't pay anything".to_string()));
+        assert_eq!(payout("3:0"), Err("payout \"3:0\" divides by zero".to_string()));
+    }
+
+    #[test]
+    fn payouts_that_are_not_ratios_are_rejected() {
+        for value in ["", "3", "3/2", "three:two", "3:2:1", "-3:2", "3:"] {
+            assert!(payout(value).is_err(), "{} parsed", value);
+        }
+    }
+
+    #[test]
+    fn default_rules_are_valid() {
+        assert_eq!(TableRules::default().validate(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn validate_rejects_a_max_bet_below_the_min_bet() {
+        let rules = TableRules { min_bet: Money::from_units(10), max_bet: Money::from_units(5), ..TableRules::default() };
+        assert_eq!(rules.validate(), ["max_bet ($5.00) is lower than min_bet ($10.00)"]);
+    }
+
+    #[test]
+    fn validate_rejects_unsupported_deck_counts() {
+        for decks in [0, 3, 4, 7] {
+            assert_eq!(TableRules { decks, ..TableRules::default() }.validate(), [format!("decks must be 1, 2, 6 or 8, got {}", decks)]);
+        }
+        for decks in [1, 2, 6, 8] {
+            assert!(TableRules { decks, ..TableRules::default() }.validate().is_empty());
+        }
+    }
+
+    #[test]
+    fn validate_rejects_penetration_outside_zero_to_one() {
+        for penetration in [0.0, -0.5, 1.01, f32::NAN] {
+            assert_eq!(TableRules { penetration, ..TableRules::default() }.validate().len(), 1, "penetration {}", penetration);
+        }
+        for penetration in [0.01, 0.75, 1.0] {
+            assert!(TableRules { penetration, ..TableRules::default() }.validate().is_empty(), "penetration {}", penetration);
+        }
+    }
+
+    #[test]
+    fn validate_rejects_no_seats_or_too_many() {
+        assert_eq!(TableRules { seats: Vec::new(), ..TableRules::default() }.validate(), ["seats must list 1 to 7 seats, got 0"]);
+        let crowded = vec![SeatKind::Human; MAX_SEATS + 1];
+        assert_eq!(TableRules { seats: crowded, ..TableRules::default() }.validate(), ["seats must list 1 to 7 seats, got 8"]);
+        assert!(TableRules { seats: vec![SeatKind::Human; MAX_SEATS], ..TableRules::default() }.validate().is_empty());
+    }
+
+    fn loan(loan: Money, interest_percent: u32, repay_percent: u32) -> GameOverRules {
+        GameOverRules { loan, interest_percent, repay_percent, ..GameOverRules::default() }
+    }
+
+    #[test]
+    fn loan_debt_adds_the_interest() {
+        assert_eq!(loan(Money::from_units(20), 10, 50).loan_debt(), Money::from_units(22));
+        assert_eq!(loan(Money::from_units(20), 0, 50).loan_debt(), Money::from_units(20));
+        // 10% of $0.05 is half a cent, which the house keeps
+        assert_eq!(loan(Money::from_cents(5), 10, 50).loan_debt(), Money::from_cents(6));
+    }
+
+    #[test]
+    fn repayment_takes_a_share_of_the_winnings() {
+        let rules = loan(Money::from_units(20), 10, 50);
+        let debt = Money::from_units(22);
+        assert_eq!(rules.repayment(Money::from_units(10), debt), Money::from_units(5));
+        // Half of $0.05 is two and a half cents, the odd cent goes to the debt
+        assert_eq!(rules.repayment(Money::from_cents(5), debt), Money::from_cents(3));
+    }
+
+    #[test]
+    fn repayment_never_exceeds_the_debt() {
+        let rules = loan(Money::from_units(20), 10, 50);
+        assert_eq!(rules.repayment(Money::from_units(10), Money::from_units(2)), Money::from_units(2));
+        assert_eq!(rules.repayment(Money::from_units(10), Money::ZERO), Money::ZERO);
+    }
+
+    #[test]
+    fn losing_rounds_repay_nothing() {
+        let rules = loan(Money::from_units(20), 10, 50);
+        let debt = Money::from_units(22);
+        assert_eq!(rules.repayment(Money::ZERO, debt), Money::ZERO);
+        assert_eq!(rules.repayment(-Money::from_units(10), debt), Money::ZERO);
+    }
+}
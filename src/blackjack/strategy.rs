@@ -0,0 +1,307 @@
+use super::basic_strategy::BasicStrategy;
+use super::counting::CountingSystem;
+use super::engine::{Round, Phase, Action, PlayerHand, PlayingCard};
+use super::money::Money;
+use super::rules::TableRules;
+
+// Strategies decide and bet for a seat from what a player sitting there could see, never from the shoe or the hole card.
+// The game's computer seats and autoplay, and the simulator, all play through them.
+
+pub trait Strategy: Send + Sync {
+    // The decision for whatever the round is waiting on, insurance and surrender offers included
+    fn decide(&self, view: &PlayerView) -> Action;
+
+    // The main bet for the next round, zero sits the round out. Flat bets the table minimum unless a strategy knows better.
+    fn bet(&self, view: &BankrollView) -> Money {
+        view.table_bet(view.rules.min_bet)
+    }
+}
+
+// What the seat whose turn it is can see of the round
+pub struct PlayerView<'a> {
+    pub phase: Phase,
+    pub hand: &'a PlayerHand,
+    pub hands: usize, // the seat's hands, more than one after a split
+    pub dealer_upcard: PlayingCard,
+    pub seen: &'a [PlayingCard], // every card turned face up since the shoe was shuffled
+    pub money: Money,
+    pub can_hit: bool,
+    pub can_double: bool,
+    pub can_split: bool,
+    pub can_surrender: bool,
+    pub rules: &'a TableRules,
+}
+
+// What a seat knows when it places its bet
+pub struct BankrollView<'a> {
+    pub money: Money,
+    pub history: &'a [Money], // what every round of the session won or lost, the last round last
+    pub seen: &'a [PlayingCard],
+    pub rules: &'a TableRules,
+}
+
+// The usual multi-deck chart, worked out from the table rules
+pub struct Basic {
+    chart: BasicStrategy,
+}
+
+// Stands on every total that could bust with one more card
+pub struct NeverBust;
+
+// Plays the hand by the dealer's rules
+pub struct MimicDealer;
+
+// Plays the chart, and bets and takes insurance by the true count
+pub struct Counting {
+    chart: BasicStrategy,
+    pub system: CountingSystem,
+}
+
+// Names the simulator takes for the built-in strategies
+pub const STRATEGY_NAMES: [&str; 4] = ["basic", "never-bust", "mimic-dealer", "counting"];
+
+// The counter raises the bet one unit for every true count above 1, up to this many units
+const MAX_SPREAD: i64 = 8;
+// Insurance is worth taking from this Hi-Lo true count on
+const INSURANCE_COUNT: f32 = 3.0;
+
+impl<'a> PlayerView<'a> {
+    // The active seat's view of a round that is waiting on a decision
+    pub fn new(round: &'a Round, money: Money, seen: &'a [PlayingCard], rules: &'a TableRules) -> PlayerView<'a> {
+        PlayerView {
+            phase: round.phase,
+            hand: round.current_hand(),
+            hands: round.current_seat().hands.len(),
+            dealer_upcard: round.dealer_hand[0],
+            seen,
+            money,
+            can_hit: round.can_hit(rules),
+            can_double: round.can_double(money, rules),
+            can_split: round.can_split(money, rules),
+            can_surrender: round.can_surrender(rules),
+            rules,
+        }
+    }
+}
+
+impl<'a> BankrollView<'a> {
+    // The amount rounded down to the table's bet steps and limits, zero when the seat can't cover the minimum
+    pub fn table_bet(&self, amount: Money) -> Money {
+        let rules = self.rules;
+        if self.money < rules.min_bet {
+            return Money::ZERO;
+        }
+        let steps = (amount.max(rules.min_bet) - rules.min_bet).steps(rules.bet_step);
+        (rules.min_bet + rules.bet_step * steps).min(rules.max_bet).min(self.money)
+    }
+}
+
+// Insurance, even money and early surrender are turned down, the strategies that play the chart check it for early
+// surrender first
+fn side_decision(view: &PlayerView) -> Option<Action> {
+    match view.phase {
+        Phase::EarlySurrender | Phase::Insurance | Phase::EvenMoney => Some(Action::Decline),
+        Phase::PlayerTurn | Phase::Finished => None,
+    }
+}
+
+impl Basic {
+    pub fn new(rules: &TableRules) -> Basic {
+        Basic { chart: BasicStrategy::new(rules) }
+    }
+}
+
+impl Strategy for Basic {
+    fn decide(&self, view: &PlayerView) -> Action {
+        if self.chart.surrenders_early(view) {
+            return Action::Surrender;
+        }
+        if let Some(action) = side_decision(view) {
+            return action;
+        }
+        self.chart.advise(view).unwrap_or(Action::Stand)
+    }
+}
+
+impl Strategy for NeverBust {
+    fn decide(&self, view: &PlayerView) -> Action {
+        if let Some(action) = side_decision(view) {
+            return action;
+        }
+        let hand = view.hand.eval();
+        if view.can_hit && (hand.best_total <= 11 || (hand.is_soft && hand.best_total <= 17)) {
+            Action::Hit
+        } else {
+            Action::Stand
+        }
+    }
+}
+
+impl Strategy for MimicDealer {
+    fn decide(&self, view: &PlayerView) -> Action {
+        if let Some(action) = side_decision(view) {
+            return action;
+        }
+        let hand = view.hand.eval();
+        if view.can_hit && (hand.best_total < 17 || (hand.best_total == 17 && hand.is_soft && view.rules.dealer_hits_soft_17)) {
+            Action::Hit
+        } else {
+            Action::Stand
+        }
+    }
+}
+
+impl Counting {
+    pub fn new(system: CountingSystem, rules: &TableRules) -> Counting {
+        Counting { chart: BasicStrategy::new(rules), system }
+    }
+
+    fn true_count(&self, seen: &[PlayingCard], rules: &TableRules) -> f32 {
+        self.system.true_count(seen, rules.decks)
+    }
+}
+
+impl Strategy for Counting {
+    fn decide(&self, view: &PlayerView) -> Action {
+        // Insurance pays when enough tens are left, even money is the same bet on a blackjack
+        let insure = self.true_count(view.seen, view.rules) >= INSURANCE_COUNT;
+        match view.phase {
+            Phase::Insurance if insure && view.rules.max_insurance(view.hand.bet) <= view.money => Action::Insurance(view.rules.max_insurance(view.hand.bet)),
+            Phase::EvenMoney if insure => Action::EvenMoney,
+            Phase::EarlySurrender if self.chart.surrenders_early(view) => Action::Surrender,
+            Phase::EarlySurrender | Phase::Insurance | Phase::EvenMoney => Action::Decline,
+            Phase::PlayerTurn | Phase::Finished => self.chart.advise(view).unwrap_or(Action::Stand),
+        }
+    }
+
+    fn bet(&self, view: &BankrollView) -> Money {
+        let units = ((self.true_count(view.seen, view.rules) - 1.0).floor() as i64).clamp(1, MAX_SPREAD);
+        view.table_bet(view.rules.min_bet * units)
+    }
+}
+
+// One of the built-in strategies by its name in STRATEGY_NAMES
+pub fn by_name(name: &str, rules: &TableRules) -> Option<Box<dyn Strategy>> {
+    match name {
+        "basic" => Some(Box::new(Basic::new(rules))),
+        "never-bust" => Some(Box::new(NeverBust)),
+        "mimic-dealer" => Some(Box::new(MimicDealer)),
+        "counting" => Some(Box::new(Counting::new(CountingSystem::HiLo, rules))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::engine::CardSuit;
+    use super::super::rules::SurrenderRule;
+
+    fn card(value: u8) -> PlayingCard {
+        PlayingCard { suit: CardSuit::Spade, value }
+    }
+
+    fn low_cards(count: usize) -> Vec<PlayingCard> {
+        (0..count).map(|_| card(5)).collect()
+    }
+
+    fn hand(cards: [u8; 2]) -> PlayerHand {
+        let mut hand = PlayerHand::new(Money::from_units(10));
+        hand.cards = cards.iter().map(|value| card(*value)).collect();
+        hand
+    }
+
+    // A starting hand against the upcard, with everything the rules allow on it
+    fn view<'a>(phase: Phase, hand: &'a PlayerHand, upcard: u8, seen: &'a [PlayingCard], rules: &'a TableRules) -> PlayerView<'a> {
+        PlayerView {
+            phase,
+            hand,
+            hands: 1,
+            dealer_upcard: card(upcard),
+            seen,
+            money: Money::from_units(100),
+            can_hit: true,
+            can_double: true,
+            can_split: hand.cards[0].value == hand.cards[1].value,
+            can_surrender: rules.surrender != SurrenderRule::NoSurrender,
+            rules,
+        }
+    }
+
+    #[test]
+    fn by_name_knows_every_strategy_name() {
+        let rules = TableRules::default();
+        for name in STRATEGY_NAMES {
+            assert!(by_name(name, &rules).is_some(), "{}", name);
+        }
+        assert!(by_name("martingale", &rules).is_none());
+    }
+
+    #[test]
+    fn basic_surrenders_early_when_the_chart_says_so() {
+        let early = TableRules { surrender: SurrenderRule::Early, ..TableRules::default() };
+        let basic = Basic::new(&early);
+        let sixteen = hand([10, 6]);
+        assert_eq!(basic.decide(&view(Phase::EarlySurrender, &sixteen, 1, &[], &early)), Action::Surrender);
+        assert_eq!(basic.decide(&view(Phase::EarlySurrender, &sixteen, 10, &[], &early)), Action::Surrender);
+        let seventeen = hand([10, 7]);
+        assert_eq!(basic.decide(&view(Phase::EarlySurrender, &seventeen, 10, &[], &early)), Action::Decline);
+        let eleven = hand([5, 6]);
+        assert_eq!(basic.decide(&view(Phase::EarlySurrender, &eleven, 6, &[], &early)), Action::Decline);
+        assert_eq!(basic.decide(&view(Phase::PlayerTurn, &eleven, 6, &[], &early)), Action::Double);
+    }
+
+    #[test]
+    fn basic_declines_insurance() {
+        let rules = TableRules::default();
+        let basic = Basic::new(&rules);
+        let sixteen = hand([10, 6]);
+        assert_eq!(basic.decide(&view(Phase::Insurance, &sixteen, 1, &[], &rules)), Action::Decline);
+        assert_eq!(basic.decide(&view(Phase::PlayerTurn, &sixteen, 10, &[], &rules)), Action::Surrender);
+    }
+
+    #[test]
+    fn never_bust_stands_on_hands_that_could_bust() {
+        let rules = TableRules::default();
+        assert_eq!(NeverBust.decide(&view(Phase::PlayerTurn, &hand([5, 6]), 10, &[], &rules)), Action::Hit);
+        assert_eq!(NeverBust.decide(&view(Phase::PlayerTurn, &hand([10, 2]), 10, &[], &rules)), Action::Stand);
+        assert_eq!(NeverBust.decide(&view(Phase::PlayerTurn, &hand([1, 6]), 10, &[], &rules)), Action::Hit);
+        assert_eq!(NeverBust.decide(&view(Phase::PlayerTurn, &hand([1, 7]), 10, &[], &rules)), Action::Stand);
+    }
+
+    #[test]
+    fn mimic_dealer_hits_soft_17_only_where_the_dealer_does() {
+        let s17 = TableRules { dealer_hits_soft_17: false, ..TableRules::default() };
+        let h17 = TableRules { dealer_hits_soft_17: true, ..TableRules::default() };
+        let soft_17 = hand([1, 6]);
+        assert_eq!(MimicDealer.decide(&view(Phase::PlayerTurn, &soft_17, 10, &[], &s17)), Action::Stand);
+        assert_eq!(MimicDealer.decide(&view(Phase::PlayerTurn, &soft_17, 10, &[], &h17)), Action::Hit);
+        assert_eq!(MimicDealer.decide(&view(Phase::PlayerTurn, &hand([10, 6]), 6, &[], &s17)), Action::Hit);
+        assert_eq!(MimicDealer.decide(&view(Phase::PlayerTurn, &hand([10, 7]), 6, &[], &s17)), Action::Stand);
+    }
+
+    #[test]
+    fn counting_insures_at_a_high_count() {
+        let rules = TableRules { decks: 1, ..TableRules::default() };
+        let counting = Counting::new(CountingSystem::HiLo, &rules);
+        let twenty = hand([10, 10]);
+        // Ten low cards out of one deck are a true count of about 12
+        let seen = low_cards(10);
+        assert_eq!(counting.decide(&view(Phase::Insurance, &twenty, 1, &seen, &rules)), Action::Insurance(Money::from_units(5)));
+        assert_eq!(counting.decide(&view(Phase::Insurance, &twenty, 1, &[], &rules)), Action::Decline);
+        let blackjack = hand([1, 10]);
+        assert_eq!(counting.decide(&view(Phase::EvenMoney, &blackjack, 1, &seen, &rules)), Action::EvenMoney);
+        assert_eq!(counting.decide(&view(Phase::EvenMoney, &blackjack, 1, &[], &rules)), Action::Decline);
+    }
+
+    #[test]
+    fn counting_spreads_its_bet_with_the_count() {
+        let rules = TableRules { decks: 1, max_bet: Money::from_units(100), ..TableRules::default() };
+        let counting = Counting::new(CountingSystem::HiLo, &rules);
+        let bet = |seen: &[PlayingCard]| counting.bet(&BankrollView { money: Money::from_units(100), history: &[], seen, rules: &rules });
+        assert_eq!(bet(&[]), Money::from_units(1));
+        // 4 low cards out of one deck are a true count of 4.33, three units
+        assert_eq!(bet(&low_cards(4)), Money::from_units(3));
+        assert_eq!(bet(&low_cards(20)), Money::from_units(MAX_SPREAD));
+    }
+}
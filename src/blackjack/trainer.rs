@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+use super::basic_strategy::{BasicStrategy, action_name};
+use super::engine::{Phase, Action};
+use super::strategy::PlayerView;
+
+// Grades the player's decisions against basic strategy and keeps score for every kind of hand
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HandCategory {
+    Hard,
+    Soft,
+    Pair,
+}
+
+// One graded decision
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub category: HandCategory,
+    pub spot: String, // e.g. "Hard 16 vs 10"
+    pub played: Action,
+    pub correct: Action,
+    pub ev_loss: f64, // in initial bets, what the played action gives up against the correct one
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Score {
+    right: u32,
+    wrong: u32,
+    ev_loss: f64,
+}
+
+#[derive(Default)]
+pub struct Trainer {
+    categories: BTreeMap<HandCategory, Score>,
+    spots: BTreeMap<String, Score>,
+}
+
+impl HandCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HandCategory::Hard => "Hard totals",
+            HandCategory::Soft => "Soft totals",
+            HandCategory::Pair => "Pairs",
+        }
+    }
+}
+
+impl Decision {
+    // None when the round isn't waiting on a playing decision, insurance and surrender offers aren't graded
+    pub fn grade(action: Action, view: &PlayerView, strategy: &BasicStrategy) -> Option<Decision> {
+        if view.phase != Phase::PlayerTurn {
+            return None;
+        }
+        let correct = strategy.advise(view)?;
+        let values = strategy.values(view);
+        let ev_loss = match (values.of(correct), values.of(action)) {
+            (Some(best), Some(played)) => (best - played).max(0.0),
+            _ => 0.0,
+        };
+        let hand = view.hand;
+        let eval = hand.eval();
+        let upcard = match view.dealer_upcard.value.min(10) {
+            1 => "A".to_string(),
+            value => format!("{}", value),
+        };
+        let (category, spot) = if view.can_split {
+            let pair = match hand.cards[0].value.min(10) {
+                1 => "Aces".to_string(),
+                value => format!("{}s", value),
+            };
+            (HandCategory::Pair, format!("Pair of {} vs {}", pair, upcard))
+        } else if eval.is_soft {
+            (HandCategory::Soft, format!("Soft {} vs {}", eval.best_total, upcard))
+        } else {
+            (HandCategory::Hard, format!("Hard {} vs {}", eval.hard_total, upcard))
+        };
+        Some(Decision { category, spot, played: action, correct, ev_loss })
+    }
+
+    pub fn is_correct(&self) -> bool {
+        self.played == self.correct
+    }
+
+    // What the player is told right after the decision
+    pub fn feedback(&self) -> String {
+        if self.is_correct() {
+            format!("Correct! {}: {}", self.spot, action_name(self.correct))
+        } else {
+            format!("Mistake! {}: basic strategy says {}, not {} ({:.3} bets lost on average)", self.spot, action_name(self.correct), action_name(self.played), self.ev_loss)
+        }
+    }
+}
+
+impl Score {
+    fn add(&mut self, decision: &Decision) {
+        if decision.is_correct() {
+            self.right += 1;
+        } else {
+            self.wrong += 1;
+        }
+        self.ev_loss += decision.ev_loss;
+    }
+
+    fn total(&self) -> u32 {
+        self.right + self.wrong
+    }
+
+    fn accuracy(&self) -> f32 {
+        if self.total() == 0 {
+            return 0.0;
+        }
+        self.right as f32 / self.total() as f32 * 100.0
+    }
+}
+
+impl Trainer {
+    pub fn record(&mut self, decision: &Decision) {
+        self.categories.entry(decision.category).or_default().add(decision);
+        self.spots.entry(decision.spot.clone()).or_default().add(decision);
+    }
+
+    // Correct decisions and decisions made so far
+    pub fn score(&self) -> (u32, u32) {
+        let right = self.categories.values().map(|score| score.right).sum();
+        let total = self.categories.values().map(|score| score.total()).sum();
+        (right, total)
+    }
+
+    // Accuracy per hand category and the spots with the most mistakes
+    pub fn report(&self) -> String {
+        let (right, total) = self.score();
+        if total == 0 {
+            return "No decisions were graded this session".to_string();
+        }
+        let mut report = format!("Training session: {} of {} decisions correct ({:.1}%)", right, total, right as f32 / total as f32 * 100.0);
+        let ev_loss: f64 = self.categories.values().map(|score| score.ev_loss).sum();
+        if ev_loss > 0.0 {
+            report.push_str(&format!(", the mistakes cost {:.3} bets on average", ev_loss));
+        }
+        for (category, score) in self.categories.iter() {
+            report.push_str(&format!("\n  {}: {} of {} ({:.1}%)", category.name(), score.right, score.total(), score.accuracy()));
+        }
+
+        let mut weakest: Vec<(&String, &Score)> = self.spots.iter().filter(|(_, score)| score.wrong > 0).collect();
+        weakest.sort_by(|a, b| b.1.wrong.cmp(&a.1.wrong).then(a.1.accuracy().partial_cmp(&b.1.accuracy()).unwrap()));
+        if weakest.is_empty() {
+            report.push_str("\nNo mistakes, well played!");
+        } else {
+            report.push_str("\nWeakest spots:");
+            for (spot, score) in weakest.iter().take(5) {
+                let mistakes = if score.wrong == 1 { "mistake" } else { "mistakes" };
+                report.push_str(&format!("\n  {}: {} {} in {} decisions", spot, score.wrong, mistakes, score.total()));
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blackjack::engine::{CardSuit, PlayerHand, PlayingCard};
+    use crate::blackjack::money::Money;
+    use crate::blackjack::rules::TableRules;
+
+    fn hand(cards: [u8; 2]) -> PlayerHand {
+        let mut hand = PlayerHand::new(Money::from_units(10));
+        hand.cards = cards.iter().map(|value| PlayingCard { suit: CardSuit::Spade, value: *value }).collect();
+        hand
+    }
+
+    // A starting hand against the upcard at a table without surrender
+    fn view<'a>(phase: Phase, hand: &'a PlayerHand, upcard: u8, rules: &'a TableRules) -> PlayerView<'a> {
+        PlayerView {
+            phase,
+            hand,
+            hands: 1,
+            dealer_upcard: PlayingCard { suit: CardSuit::Heart, value: upcard },
+            seen: &[],
+            money: Money::from_units(100),
+            can_hit: true,
+            can_double: true,
+            can_split: hand.cards[0].value == hand.cards[1].value,
+            can_surrender: false,
+            rules,
+        }
+    }
+
+    fn grade(action: Action, cards: [u8; 2], upcard: u8) -> Decision {
+        let rules = TableRules::default();
+        let hand = hand(cards);
+        Decision::grade(action, &view(Phase::PlayerTurn, &hand, upcard, &rules), &BasicStrategy::new(&rules)).unwrap()
+    }
+
+    #[test]
+    fn playing_the_chart_is_correct() {
+        let decision = grade(Action::Stand, [10, 7], 10);
+        assert_eq!(decision.spot, "Hard 17 vs 10");
+        assert!(decision.is_correct());
+        assert_eq!(decision.ev_loss, 0.0);
+        assert_eq!(grade(Action::Split, [8, 8], 1).category, HandCategory::Pair);
+    }
+
+    #[test]
+    fn a_mistake_records_the_chart_action_and_what_it_costs() {
+        let decision = grade(Action::Stand, [5, 6], 6);
+        assert!(!decision.is_correct());
+        assert_eq!(decision.spot, "Hard 11 vs 6");
+        assert_eq!((decision.played, decision.correct), (Action::Stand, Action::Double));
+
+        let rules = TableRules::default();
+        let eleven = hand([5, 6]);
+        let values = BasicStrategy::new(&rules).values(&view(Phase::PlayerTurn, &eleven, 6, &rules));
+        assert_eq!(decision.ev_loss, values.double - values.stand);
+        assert!(decision.ev_loss > 0.5, "standing on 11 gives up {}", decision.ev_loss);
+    }
+
+    #[test]
+    fn insurance_offers_are_not_graded() {
+        let rules = TableRules::default();
+        let sixteen = hand([10, 6]);
+        let offer = view(Phase::Insurance, &sixteen, 1, &rules);
+        assert_eq!(Decision::grade(Action::Decline, &offer, &BasicStrategy::new(&rules)), None);
+    }
+
+    #[test]
+    fn the_report_adds_up_every_category_and_lists_the_weakest_spots() {
+        let mut trainer = Trainer::default();
+        let mistake = grade(Action::Hit, [10, 7], 10);
+        for decision in [grade(Action::Stand, [10, 7], 10), mistake.clone(), mistake.clone(), grade(Action::Stand, [1, 8], 6), grade(Action::Split, [8, 8], 10)] {
+            trainer.record(&decision);
+        }
+        assert_eq!(trainer.score(), (3, 5));
+        let report = trainer.report();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[0], format!("Training session: 3 of 5 decisions correct (60.0%), the mistakes cost {:.3} bets on average", mistake.ev_loss * 2.0));
+        assert_eq!(&lines[1..], [
+            "  Hard totals: 1 of 3 (33.3%)",
+            "  Soft totals: 1 of 1 (100.0%)",
+            "  Pairs: 1 of 1 (100.0%)",
+            "Weakest spots:",
+            "  Hard 17 vs 10: 2 mistakes in 3 decisions",
+        ]);
+    }
+
+    #[test]
+    fn an_empty_session_has_nothing_to_report() {
+        assert_eq!(Trainer::default().report(), "No decisions were graded this session");
+    }
+}
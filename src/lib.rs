@@ -0,0 +1,11 @@
+// The parts of the game that don't need Bevy, shared by the game and the kasino-sim binary
+pub mod blackjack {
+    pub mod engine;
+    pub mod money;
+    pub mod rules;
+    pub mod basic_strategy;
+    pub mod trainer;
+    pub mod counting;
+    pub mod strategy;
+    pub mod ai;
+}
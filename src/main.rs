@@ -1,6 +1,15 @@
 use bevy::{prelude::*, render::camera::ScalingMode, window::WindowMode::*};
 mod blackjack;
-use blackjack::BlackjackPlugin;
+use blackjack::{BlackjackPlugin, BlackjackState, Rules};
+use kasino::blackjack::engine::{ShoeRng, seeded_rng, random_seed};
+use kasino::blackjack::money::Money;
+use kasino::blackjack::rules::SeatKind;
+use kasino::blackjack::strategy::Strategy;
+
+mod profile;
+use profile::Profile;
+mod start_screen;
+use start_screen::StartScreenPlugin;
 
 // debug
 mod debug;
@@ -32,6 +41,7 @@ fn main() {
         //.add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
         .add_startup_system_to_stage(StartupStage::PreStartup,setup_system)
         .add_plugin(BlackjackPlugin)
+        .add_plugin(StartScreenPlugin)
         .add_plugin(DebugPlugin) // debug
         .add_system(update_ui_system)
         .run();
@@ -49,16 +59,34 @@ struct SFXPlayCard(Handle<AudioSource>);
 #[derive(Component)]
 pub struct UiPlayerMoney;
 
+// The game's only source of randomness, every shuffle and draw goes through it so a seed replays the same shoes
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    rng: ShoeRng,
+}
+
+// Everyone at the table in seat order. The bankrolls last for the whole session.
 #[derive(Resource)]
+pub struct Players {
+    seats: Vec<Player>,
+    current: usize, // the seat the keyboard bets and plays for
+    owner: Option<usize>, // the seat the active profile plays at, the first human
+}
+
 pub struct Player {
-    money: f32,
-    bet: f32,
+    strategy: Option<Box<dyn Strategy>>, // None for a human at the keyboard
+    history: Vec<Money>, // net result of every round played this session
+    profile: Profile, // name, bankroll, debt and last bet, only the owner's is saved
+    insurance: Money,
+    side_bets: Vec<Money>, // wager on each of the table's side bets
 }
 
 fn setup_system(
 	mut commands: Commands,
 	asset_server: Res<AssetServer>,
 	mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    rules: Res<Rules>,
 ) {
 
     // load audio
@@ -90,12 +118,42 @@ fn setup_system(
 	};
 	commands.insert_resource(game_textures);
 
-    // setup Player values
-    let player = Player {
-        money: 5.0,
-        bet: 1.0,
+    // the first human plays with the profile picked on the start screen, everyone else starts over every session
+    let owner = rules.seats.iter().position(|seat| *seat == SeatKind::Human);
+
+    // setup a Player for every seat
+    let humans = rules.seats.iter().filter(|seat| **seat == SeatKind::Human).count();
+    let mut seats: Vec<Player> = Vec::new();
+    for (i, seat) in rules.seats.iter().enumerate() {
+        // A second seat of the same kind gets a number
+        let before = rules.seats[..i].iter().filter(|other| *other == seat).count();
+        let (name, strategy) = match seat {
+            SeatKind::Human if humans == 1 => ("Player".to_string(), None),
+            SeatKind::Human => (format!("Player {}", before + 1), None),
+            SeatKind::Computer(personality) => {
+                let name = if before == 0 { format!("{}", personality) } else { format!("{} {}", personality, before + 1) };
+                (name, Some(personality.strategy(&rules)))
+            },
+        };
+        seats.push(Player {
+            strategy,
+            history: Vec::new(),
+            profile: Profile::guest(&name, &rules),
+            insurance: Money::ZERO,
+            side_bets: vec![Money::ZERO; rules.side_bets.len()],
+        });
+    }
+    let players = Players {
+        current: seats.iter().position(|player| player.strategy.is_none()).unwrap_or(0),
+        seats,
+        owner,
     };
-    commands.insert_resource(player); 
+    commands.insert_resource(players); 
+
+    // setup the shuffle seed, the command line wins over the rules file
+    let seed = seed_argument().or(rules.seed).unwrap_or_else(random_seed);
+    println!("Shoe seed: {}", seed);
+    commands.insert_resource(GameRng { seed, rng: seeded_rng(seed) });
 
     commands
     .spawn(Text2dBundle {
@@ -113,11 +171,90 @@ fn setup_system(
 
 }
 
+// kasino --seed 1234 replays the shoes of an earlier session
+fn seed_argument() -> Option<u64> {
+    let args: Vec<String> = std::env::args().collect();
+    let position = args.iter().position(|arg| arg == "--seed")?;
+    match args.get(position + 1).map(|seed| seed.parse()) {
+        Some(Ok(seed)) => Some(seed),
+        _ => {
+            eprintln!("--seed needs a whole number, picking a random seed instead");
+            None
+        }
+    }
+}
+
+impl Players {
+    pub fn current(&self) -> &Player {
+        &self.seats[self.current]
+    }
+
+    pub fn current_mut(&mut self) -> &mut Player {
+        &mut self.seats[self.current]
+    }
+
+    // The keyboard goes back to the first human between rounds
+    pub fn first_human(&self) -> usize {
+        self.seats.iter().position(|player| player.strategy.is_none()).unwrap_or(0)
+    }
+
+    // The next human after the current one, round the table
+    pub fn next_human(&self) -> usize {
+        (1..=self.seats.len())
+            .map(|i| (self.current + i) % self.seats.len())
+            .find(|i| self.seats[*i].strategy.is_none())
+            .unwrap_or(self.current)
+    }
+
+    // The profile picked on the start screen, None when every seat is a computer
+    pub fn profile(&self) -> Option<&Profile> {
+        self.owner.map(|owner| &self.seats[owner].profile)
+    }
+
+    pub fn profile_mut(&mut self) -> Option<&mut Profile> {
+        self.owner.map(move |owner| &mut self.seats[owner].profile)
+    }
+
+    // The engine settles every seat at once, the bankrolls are handed to it and taken back after each call
+    pub fn bankrolls(&self) -> Vec<Money> {
+        self.seats.iter().map(|player| player.profile.money).collect()
+    }
+
+    pub fn set_bankrolls(&mut self, bankrolls: Vec<Money>) {
+        for (player, money) in self.seats.iter_mut().zip(bankrolls) {
+            player.profile.money = money;
+        }
+    }
+}
+
 pub fn update_ui_system(
-    player: ResMut<Player>,
+    players: Res<Players>,
+    rules: Res<Rules>,
+    blackjack_state: Res<State<BlackjackState>>,
     mut query: Query<&mut Text, With<UiPlayerMoney>>,
 ) {
+    let player = players.current();
     for mut text in query.iter_mut() {
-        text.sections[0].value = format!("Money: {} Bet {}", player.money, player.bet);
+        // The start screen shows every profile's bankroll instead
+        if *blackjack_state.current() == BlackjackState::StartScreen {
+            text.sections[0].value = String::new();
+            continue;
+        }
+        let mut value = format!("Money: {} Bet {}", player.profile.money, player.profile.bet);
+        if players.seats.len() > 1 {
+            value.insert_str(0, &format!("{} ", player.profile.name));
+        }
+        for (side_bet, wager) in rules.side_bets.iter().zip(player.side_bets.iter()) {
+            if wager.is_positive() {
+                value.push_str(&format!(" {} {}", side_bet.name, wager));
+            }
+        }
+        if player.insurance.is_positive() {
+            value.push_str(&format!(" Insurance {}", player.insurance));
+        }
+        if player.profile.debt.is_positive() {
+            value.push_str(&format!(" Debt {}", player.profile.debt));
+        }
+        text.sections[0].value = value;
     }
 }
@@ -0,0 +1,397 @@
+use std::{fmt, fs, io::{self, Write}, path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use kasino::blackjack::counting::CountingSystem;
+use kasino::blackjack::engine::{Outcome, Settlement};
+use kasino::blackjack::money::Money;
+use kasino::blackjack::rules::TableRules;
+
+pub const PROFILES_DIR: &str = "profiles";
+// Saves from before there were several profiles, moved into PROFILES_DIR on the first launch
+pub const OLD_PROFILE_FILE: &str = "profile.ron";
+pub const MAX_NAME_LENGTH: usize = 16;
+
+// A player's bankroll, lifetime stats and preferences, kept between sessions as one file per profile in the user's data
+// directory. Every seat plays with a profile, the one picked on the start screen sits at the first human seat and is
+// saved after every settled round, the other seats get one that's never saved. Fields missing from the file keep their
+// default value.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Profile {
+    #[serde(skip)]
+    path: Option<PathBuf>, // None when there's no data directory to save to
+    pub name: String,
+    pub last_played: Option<u64>, // seconds since the Unix epoch
+    pub money: Money,
+    pub debt: Money, // what's left to pay back on a loan
+    pub bet: Money, // the main bet of the last round, a bet the table doesn't take starts at the minimum
+    pub stats: LifetimeStats,
+    pub preferences: Preferences,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LifetimeStats {
+    pub rounds: u64,
+    pub hands: u64,
+    pub wins: u64,
+    pub blackjacks: u64, // counted in wins too
+    pub pushes: u64,
+    pub losses: u64,
+    pub surrenders: u64,
+    pub wagered: Money,
+    pub net: Money,
+    pub biggest_win: Money, // the best net result of a single round
+    pub best_bankroll: Money,
+    pub bailouts: u64,
+    pub loans: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Preferences {
+    pub advice: bool,
+    pub counting_system: CountingSystem,
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Read(io::Error),
+    Parse(ron::error::SpannedError),
+    Write(io::Error),
+    Serialize(ron::Error),
+}
+
+impl Default for Preferences {
+    fn default() -> Preferences {
+        Preferences {
+            advice: false,
+            counting_system: CountingSystem::HiLo,
+        }
+    }
+}
+
+impl Profile {
+    // A new profile starts with the table's starting money
+    pub fn new(name: &str, rules: &TableRules) -> Profile {
+        Profile {
+            path: new_profile_path(),
+            ..Profile::guest(name, rules)
+        }
+    }
+
+    // A profile for a seat that starts over every session, it has no file to be saved to
+    pub fn guest(name: &str, rules: &TableRules) -> Profile {
+        Profile {
+            name: name.to_string(),
+            money: rules.starting_money,
+            bet: rules.min_bet,
+            stats: LifetimeStats { best_bankroll: rules.starting_money, ..default() },
+            ..default()
+        }
+    }
+
+    // The last bet is kept when the table still takes it
+    pub fn fit_bet(&mut self, rules: &TableRules) {
+        if self.bet < rules.min_bet || self.bet > rules.max_bet {
+            self.bet = rules.min_bet;
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Profile, ProfileError> {
+        let file = fs::read_to_string(path).map_err(ProfileError::Read)?;
+        let mut profile: Profile = ron::from_str(&file).map_err(ProfileError::Parse)?;
+        profile.path = Some(path.to_path_buf());
+        if profile.name.trim().is_empty() {
+            profile.name = "Player".to_string();
+        }
+        Ok(profile)
+    }
+
+    // The new save is written next to the old one and renamed over it, so a crash mid write never leaves half a file
+    // behind. The previous save is kept as a backup for when the new one turns out to be unreadable.
+    pub fn save(&self) -> Result<(), ProfileError> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(ProfileError::Serialize)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(ProfileError::Write)?;
+        }
+        let temporary = path.with_extension("ron.tmp");
+        let mut file = fs::File::create(&temporary).map_err(ProfileError::Write)?;
+        file.write_all(text.as_bytes()).map_err(ProfileError::Write)?;
+        file.sync_all().map_err(ProfileError::Write)?;
+        if path.exists() {
+            fs::copy(path, backup_path(path)).map_err(ProfileError::Write)?;
+        }
+        fs::rename(&temporary, path).map_err(ProfileError::Write)?;
+        Ok(())
+    }
+
+    // A profile that can't be read is moved aside, so it isn't overwritten by the next autosave, and the backup of the
+    // save before it is used instead when there is one
+    pub fn load_or_recover(path: &Path) -> Option<Profile> {
+        let error = match Profile::load(path) {
+            Ok(profile) => return Some(profile),
+            Err(error) => error,
+        };
+        eprintln!("Could not load the profile in {}: {}", path.display(), error);
+        let corrupted = path.with_extension("ron.corrupted");
+        match fs::rename(path, &corrupted) {
+            Ok(()) => eprintln!("The unreadable save was moved to {}", corrupted.display()),
+            Err(error) => eprintln!("Could not move the unreadable save aside ({})", error),
+        }
+        match Profile::load(&backup_path(path)) {
+            Ok(mut profile) => {
+                eprintln!("Restored the previous save of {}, bankroll {} after {} rounds", profile.name, profile.money, profile.stats.rounds);
+                profile.path = Some(path.to_path_buf());
+                // Written back under the original name, only .ron files are listed
+                if let Err(error) = profile.save() {
+                    eprintln!("Could not save the restored profile to {}: {}", path.display(), error);
+                }
+                Some(profile)
+            },
+            Err(_) => {
+                eprintln!("No usable backup either, the profile is left out");
+                None
+            },
+        }
+    }
+
+    // Removes the save and its backup
+    pub fn delete(&self) -> Result<(), ProfileError> {
+        if let Some(path) = &self.path {
+            fs::remove_file(path).map_err(ProfileError::Write)?;
+            let _ = fs::remove_file(backup_path(path)); // there's no backup before the second save
+        }
+        Ok(())
+    }
+
+    // A copy under a new name and its own file, with the same bankroll, stats and preferences, that hasn't been played yet
+    pub fn duplicate(&self, name: &str) -> Profile {
+        Profile {
+            path: new_profile_path(),
+            name: name.to_string(),
+            last_played: None,
+            ..self.clone()
+        }
+    }
+
+    // Guests and profiles made without a data directory are never written
+    pub fn is_saved(&self) -> bool {
+        self.path.is_some()
+    }
+
+    pub fn touch(&mut self) {
+        self.last_played = Some(now());
+    }
+
+    // Adds a settled round of the profile's seat to the lifetime stats, the bankroll has already been paid
+    pub fn record(&mut self, settlement: &Settlement) {
+        let stats = &mut self.stats;
+        stats.rounds += 1;
+        for outcome in settlement.outcomes.iter() {
+            stats.hands += 1;
+            match outcome {
+                Outcome::Win | Outcome::EvenMoney => stats.wins += 1,
+                Outcome::Blackjack => {
+                    stats.wins += 1;
+                    stats.blackjacks += 1;
+                },
+                Outcome::Push => stats.pushes += 1,
+                Outcome::Lose => stats.losses += 1,
+                Outcome::Surrendered => stats.surrenders += 1,
+            }
+        }
+        stats.wagered += settlement.wagered;
+        stats.net += settlement.net();
+        stats.biggest_win = stats.biggest_win.max(settlement.net());
+        stats.best_bankroll = stats.best_bankroll.max(self.money);
+    }
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProfileError::Read(error) => write!(f, "could not read the file ({})", error),
+            ProfileError::Parse(error) => write!(f, "could not parse the file ({})", error),
+            ProfileError::Write(error) => write!(f, "could not write the file ({})", error),
+            ProfileError::Serialize(error) => write!(f, "could not write the profile ({})", error),
+        }
+    }
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    path.with_extension("ron.bak")
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs())
+}
+
+// ~/.local/share/kasino on Linux, the usual application data folders elsewhere
+fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("kasino"))
+}
+
+// Profiles are saved as 1.ron, 2.ron and so on, a rename doesn't move the file
+fn new_profile_path() -> Option<PathBuf> {
+    next_profile_path(&data_dir()?.join(PROFILES_DIR))
+}
+
+// The first number with neither a save nor a backup left of a deleted profile
+fn next_profile_path(dir: &Path) -> Option<PathBuf> {
+    (1..).map(|i| dir.join(format!("{}.ron", i))).find(|path| !path.exists() && !backup_path(path).exists())
+}
+
+// Every profile that can be read, the one played last first. The save of the single profile of earlier versions
+// becomes the first profile.
+pub fn load_profiles() -> Vec<Profile> {
+    match data_dir() {
+        Some(dir) => load_profiles_in(&dir),
+        None => {
+            eprintln!("No data directory found, profiles won't be saved this session");
+            Vec::new()
+        }
+    }
+}
+
+fn load_profiles_in(dir: &Path) -> Vec<Profile> {
+    let profiles_dir = dir.join(PROFILES_DIR);
+    let old_save = dir.join(OLD_PROFILE_FILE);
+    if old_save.exists() && !profiles_dir.exists() {
+        let moved = fs::create_dir_all(&profiles_dir).and_then(|_| fs::rename(&old_save, profiles_dir.join("1.ron")));
+        match moved {
+            Ok(()) => {
+                let _ = fs::rename(backup_path(&old_save), backup_path(&profiles_dir.join("1.ron")));
+                println!("Moved the saved profile to {}", profiles_dir.display());
+            },
+            Err(error) => eprintln!("Could not move the saved profile to {} ({})", profiles_dir.display(), error),
+        }
+    }
+
+    let mut profiles = Vec::new();
+    if let Ok(entries) = fs::read_dir(&profiles_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == "ron") {
+                profiles.extend(Profile::load_or_recover(&path));
+            }
+        }
+    }
+    profiles.sort_by(|a, b| b.last_played.cmp(&a.last_played).then_with(|| a.name.cmp(&b.name)));
+    profiles
+}
+
+// Names are kept short enough for the start screen and unique, ignoring case
+pub fn check_name(name: &str, taken: &[&str]) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("The name can't be empty".to_string());
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!("Names are at most {} letters long", MAX_NAME_LENGTH));
+    }
+    if taken.iter().any(|other| other.eq_ignore_ascii_case(name)) {
+        return Err(format!("There's already a profile called {}", name));
+    }
+    Ok(name.to_string())
+}
+
+// How long ago the profile was played, like 3 days ago
+pub fn last_played_text(last_played: Option<u64>) -> String {
+    let seconds = match last_played {
+        Some(time) => now().saturating_sub(time),
+        None => return "never played".to_string(),
+    };
+    let minutes = seconds / 60;
+    let hours = minutes / 60;
+    let days = hours / 24;
+    if minutes < 1 {
+        "played just now".to_string()
+    } else if hours < 1 {
+        format!("played {} min ago", minutes)
+    } else if days < 1 {
+        format!("played {} h ago", hours)
+    } else if days == 1 {
+        "played yesterday".to_string()
+    } else {
+        format!("played {} days ago", days)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An empty directory of its own for every test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kasino-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join(PROFILES_DIR)).unwrap();
+        dir
+    }
+
+    #[test]
+    fn a_corrupted_save_is_restored_from_the_backup_and_stays_listed() {
+        let dir = test_dir("recover");
+        let path = dir.join(PROFILES_DIR).join("1.ron");
+        let mut profile = Profile { path: Some(path.clone()), ..Profile::guest("Ann", &TableRules::default()) };
+        profile.save().unwrap();
+        profile.money = Money::from_units(42);
+        profile.save().unwrap(); // the first save becomes the backup
+        fs::write(&path, "not a profile").unwrap();
+
+        let restored = Profile::load_or_recover(&path).unwrap();
+        assert_eq!(restored.money, TableRules::default().starting_money);
+        assert!(dir.join(PROFILES_DIR).join("1.ron.corrupted").exists());
+
+        let profiles = load_profiles_in(&dir);
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].name, "Ann");
+        assert_eq!(profiles[0].money, TableRules::default().starting_money);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn names_are_checked() {
+        assert_eq!(check_name("  Ann ", &[]), Ok("Ann".to_string()));
+        assert_eq!(check_name("   ", &[]), Err("The name can't be empty".to_string()));
+        assert_eq!(check_name(&"A".repeat(MAX_NAME_LENGTH), &[]), Ok("A".repeat(MAX_NAME_LENGTH)));
+        assert_eq!(check_name(&"A".repeat(MAX_NAME_LENGTH + 1), &[]), Err(format!("Names are at most {} letters long", MAX_NAME_LENGTH)));
+        assert_eq!(check_name("ann", &["Bob", "Ann"]), Err("There's already a profile called ann".to_string()));
+    }
+
+    #[test]
+    fn new_profiles_skip_numbers_with_a_backup_left() {
+        let dir = test_dir("numbers").join(PROFILES_DIR);
+        assert_eq!(next_profile_path(&dir), Some(dir.join("1.ron")));
+        fs::write(dir.join("1.ron"), "").unwrap();
+        fs::write(dir.join("2.ron.bak"), "").unwrap();
+        assert_eq!(next_profile_path(&dir), Some(dir.join("3.ron")));
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn a_saved_profile_loads_as_it_was() {
+        let dir = test_dir("round-trip");
+        let path = dir.join(PROFILES_DIR).join("1.ron");
+        let mut profile = Profile { path: Some(path.clone()), ..Profile::guest("Ann", &TableRules::default()) };
+        profile.money = Money::from_cents(4250);
+        profile.debt = Money::from_units(22);
+        profile.bet = Money::from_units(3);
+        profile.preferences = Preferences { advice: true, counting_system: CountingSystem::Zen };
+        profile.save().unwrap();
+
+        let loaded = Profile::load(&path).unwrap();
+        assert_eq!(loaded.name, "Ann");
+        assert_eq!(loaded.money, Money::from_cents(4250));
+        assert_eq!(loaded.debt, Money::from_units(22));
+        assert_eq!(loaded.bet, Money::from_units(3));
+        assert!(loaded.preferences.advice);
+        assert_eq!(loaded.preferences.counting_system, CountingSystem::Zen);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+use bevy::prelude::*;
+use crate::blackjack::{BlackjackState, Rules, TableRules};
+use crate::Players;
+use crate::profile::{self, Profile, MAX_NAME_LENGTH};
+
+// The saved profiles are listed before the game starts, and again after Esc between rounds. The one picked with C
+// sits at the first human seat, and profiles can be created, renamed, duplicated and deleted here.
+pub struct StartScreenPlugin;
+
+impl Plugin for StartScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_startup_system_to_stage(StartupStage::PreStartup, load_profiles_system)
+        .add_system_set(
+            SystemSet::on_enter(BlackjackState::StartScreen)
+                .with_system(enter_start_screen_system))
+        .add_system_set(
+            SystemSet::on_update(BlackjackState::StartScreen)
+                .with_system(start_screen_control_system)
+                .with_system(update_start_screen_text_system))
+        .add_system_set(
+            SystemSet::on_exit(BlackjackState::StartScreen)
+                .with_system(exit_start_screen_system));
+    }
+}
+
+#[derive(Component)]
+struct UiStartScreen;
+
+#[derive(Resource)]
+struct StartScreen {
+    profiles: Vec<Profile>,
+    selected: usize,
+    editing: Option<NameEdit>,
+    confirm_delete: bool, // D has to be pressed twice
+    message: String, // what went wrong with the last command
+}
+
+// A name being typed for a new, renamed or duplicated profile
+struct NameEdit {
+    purpose: NamePurpose,
+    name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NamePurpose {
+    New,
+    Rename,
+    Duplicate,
+}
+
+impl StartScreen {
+    // Every name in use, apart from the profile being renamed
+    fn taken_names(&self, purpose: NamePurpose) -> Vec<&str> {
+        self.profiles.iter().enumerate()
+            .filter(|(i, _)| purpose != NamePurpose::Rename || *i != self.selected)
+            .map(|(_, profile)| profile.name.as_str())
+            .collect()
+    }
+
+    fn select(&mut self, name: &str) {
+        self.selected = self.profiles.iter().position(|profile| profile.name == name).unwrap_or(0);
+    }
+}
+
+// The first launch gets a profile to play with straight away
+fn load_profiles_system(
+    mut commands: Commands,
+    rules: Res<Rules>,
+) {
+    let mut profiles = profile::load_profiles();
+    if profiles.is_empty() {
+        let profile = Profile::new("Player", &rules);
+        if let Err(error) = profile.save() {
+            eprintln!("Could not save the new profile: {}", error);
+        }
+        profiles.push(profile);
+    }
+    commands.insert_resource(StartScreen {
+        profiles,
+        selected: 0,
+        editing: None,
+        confirm_delete: false,
+        message: String::new(),
+    });
+}
+
+// Coming back from the table the list is read again, so it shows the rounds just played
+fn enter_start_screen_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    players: Res<Players>,
+    mut screen: ResMut<StartScreen>,
+) {
+    if let Some(active) = players.profile().filter(|active| active.is_saved()) {
+        screen.profiles = profile::load_profiles();
+        screen.select(&active.name);
+    }
+    screen.editing = None;
+    screen.confirm_delete = false;
+    screen.message = String::new();
+
+    commands
+    .spawn(Text2dBundle {
+        transform: Transform {
+            translation: Vec3::new(-500.0, 450.0, 100.0),
+            ..default()
+        },
+        text: Text::from_section("", TextStyle {
+                font: asset_server.load("retro_gaming.ttf"),
+                font_size: 40.0,
+                color: Color::WHITE,
+            }),
+        ..default()
+    }).insert(UiStartScreen);
+}
+
+fn exit_start_screen_system(
+    mut commands: Commands,
+    query: Query<Entity, With<UiStartScreen>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn start_screen_control_system(
+    mut keyboard: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut screen: ResMut<StartScreen>,
+    mut players: ResMut<Players>,
+    mut blackjack_state: ResMut<State<BlackjackState>>,
+    rules: Res<Rules>,
+) {
+    // Typing a name, the letters that are also commands only count as letters
+    if let Some(edit) = screen.editing.as_mut() {
+        for character in characters.iter() {
+            if !character.char.is_control() && edit.name.chars().count() < MAX_NAME_LENGTH {
+                edit.name.push(character.char);
+            }
+        }
+        if keyboard.just_pressed(KeyCode::Back) {
+            keyboard.clear_just_pressed(KeyCode::Back);
+            edit.name.pop();
+        } else if keyboard.just_pressed(KeyCode::Escape) {
+            keyboard.clear_just_pressed(KeyCode::Escape);
+            screen.editing = None;
+        } else if keyboard.just_pressed(KeyCode::Return) {
+            keyboard.clear_just_pressed(KeyCode::Return);
+            finish_edit(&mut screen, &rules);
+        }
+        return;
+    }
+    // The letter of the key that starts typing isn't part of the name
+    characters.clear();
+
+    let command = [KeyCode::Z, KeyCode::X, KeyCode::C, KeyCode::N, KeyCode::R, KeyCode::V, KeyCode::D]
+        .into_iter()
+        .find(|key| keyboard.just_pressed(*key));
+    let command = match command {
+        Some(key) => key,
+        None => return,
+    };
+    keyboard.clear_just_pressed(command);
+    screen.message = String::new();
+    let confirm_delete = screen.confirm_delete;
+    screen.confirm_delete = false;
+    let selected = screen.selected;
+    match command {
+        KeyCode::Z => screen.selected = selected.saturating_sub(1),
+        KeyCode::X => screen.selected = (selected + 1).min(screen.profiles.len().saturating_sub(1)),
+        KeyCode::C => match screen.profiles.get(selected) {
+            Some(profile) => {
+                if let Some(active) = players.profile_mut() {
+                    *active = profile.clone();
+                }
+                blackjack_state.set(BlackjackState::PreGame).unwrap();
+            },
+            None => screen.message = "Create a profile with N first".to_string(),
+        },
+        KeyCode::N => screen.editing = Some(NameEdit { purpose: NamePurpose::New, name: String::new() }),
+        KeyCode::R | KeyCode::V => {
+            let purpose = if command == KeyCode::R { NamePurpose::Rename } else { NamePurpose::Duplicate };
+            if let Some(profile) = screen.profiles.get(selected) {
+                screen.editing = Some(NameEdit { purpose, name: profile.name.clone() });
+            }
+        },
+        KeyCode::D if selected < screen.profiles.len() => {
+            if !confirm_delete {
+                screen.confirm_delete = true;
+                screen.message = format!("Press D again to delete {}", screen.profiles[selected].name);
+                return;
+            }
+            let profile = screen.profiles.remove(selected);
+            match profile.delete() {
+                Ok(()) => screen.message = format!("Deleted {}", profile.name),
+                Err(error) => screen.message = format!("Could not delete {}: {}", profile.name, error),
+            }
+            screen.selected = selected.min(screen.profiles.len().saturating_sub(1));
+        },
+        _ => {},
+    }
+}
+
+// Creates, renames or duplicates the profile once the name is typed, a name that can't be used is explained instead
+fn finish_edit(screen: &mut StartScreen, rules: &TableRules) {
+    let edit = match screen.editing.take() {
+        Some(edit) => edit,
+        None => return,
+    };
+    let name = match profile::check_name(&edit.name, &screen.taken_names(edit.purpose)) {
+        Ok(name) => name,
+        Err(problem) => {
+            screen.message = problem;
+            screen.editing = Some(edit);
+            return;
+        }
+    };
+    let profile = match edit.purpose {
+        NamePurpose::New => Profile::new(&name, rules),
+        NamePurpose::Duplicate => screen.profiles[screen.selected].duplicate(&name),
+        NamePurpose::Rename => {
+            let profile = &mut screen.profiles[screen.selected];
+            profile.name = name.clone();
+            profile.clone()
+        },
+    };
+    if let Err(error) = profile.save() {
+        eprintln!("Could not save the profile {}: {}", profile.name, error);
+    }
+    if edit.purpose != NamePurpose::Rename {
+        screen.profiles.push(profile);
+    }
+    screen.select(&name);
+    screen.message = String::new();
+}
+
+fn update_start_screen_text_system(
+    screen: Res<StartScreen>,
+    mut query: Query<&mut Text, With<UiStartScreen>>,
+) {
+    for mut text in query.iter_mut() {
+        let mut value = "Profiles\n".to_string();
+        for (i, profile) in screen.profiles.iter().enumerate() {
+            let marker = if i == screen.selected { ">" } else { " " };
+            value.push_str(&format!("{} {:<16} {:>10}   {}\n", marker, profile.name, profile.money.to_string(), profile::last_played_text(profile.last_played)));
+        }
+        if screen.profiles.is_empty() {
+            value.push_str("  No profiles yet\n");
+        }
+        value.push('\n');
+        match &screen.editing {
+            Some(edit) => {
+                let prompt = match edit.purpose {
+                    NamePurpose::New => "New profile",
+                    NamePurpose::Rename => "Rename to",
+                    NamePurpose::Duplicate => "Copy as",
+                };
+                value.push_str(&format!("{}: {}_\nEnter: Save  Esc: Cancel\n", prompt, edit.name));
+            },
+            None => value.push_str("Z/X: Choose  C: Play\nN: New  R: Rename  V: Duplicate  D: Delete\n"),
+        }
+        value.push_str(&screen.message);
+        text.sections[0].value = value;
+    }
+}